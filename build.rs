@@ -202,9 +202,12 @@ fn main() {
     compile_slang_shader("closest_hit", "closest_hit", "closest_hit.spirv");
     compile_slang_shader("ray_gen_ris", "ray_gen_ris", "ray_gen_ris.spirv");
     compile_slang_shader("ray_gen_final", "ray_gen_final", "ray_gen_final.spirv");
+    compile_slang_shader("ray_gen_ao", "ray_gen_ao", "ray_gen_ao.spirv");
     compile_slang_shader("postprocess", "main", "postprocess.spirv");
     compile_slang_shader("denoise", "main", "denoise.spirv");
     compile_slang_shader("temporal_accumulation", "main", "temporal_accumulation.spirv");
+    compile_slang_shader("skinning", "main", "skinning.spirv");
+    compile_slang_shader("morph", "main", "morph.spirv");
 
     // egui overlay (Bevy integration). One module, two stages; each entry point is
     // emitted as a SPIR-V "main" (matches how the RT stages are handled).
@@ -47,7 +47,7 @@ fn render_and_save() -> SrResult<()> {
     let mut renderer: Renderer = Renderer::new(image_extent, image_format)?;
 
     // The instance list is owned by the caller and handed to the renderer per frame.
-    let (_scene_group, scene_instances) = renderer.load_gltf("examples/assets/ReflectionRoom.glb")?;
+    let scene_instances = renderer.load_gltf("examples/assets/ReflectionRoom.glb")?.instances;
 
     let camera = Camera::default()
         .set_position(na::Point3::new(13.0, 30.0, 25.0))
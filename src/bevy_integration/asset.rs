@@ -78,18 +78,35 @@ impl SunrayMaterial {
                 metallic_factor: self.metallic,
                 roughness_factor: self.roughness,
                 base_color_texture_index: None,
+                base_color_texture_transform: Default::default(),
                 metallic_roughness_texture_index: None,
+                metallic_roughness_texture_transform: Default::default(),
             },
             normal_texture_index: None,
+            normal_texture_transform: Default::default(),
             occlusion_texture_index: None,
+            occlusion_texture_transform: Default::default(),
+            occlusion_strength: 1.0,
             emissive_factor: self.emissive,
             emissive_strength: self.emissive_strength,
             emissive_texture_index: None,
+            emissive_texture_transform: Default::default(),
             alpha_mode: gltf::material::AlphaMode::Opaque,
             alpha_cutoff: 0.5,
             double_sided: false,
             transmission_factor: self.transmission,
             ior: self.ior,
+            thickness_factor: 0.0,
+            attenuation_distance: f32::INFINITY,
+            attenuation_color: [1.0; 3],
+            clearcoat_factor: 0.0,
+            clearcoat_roughness_factor: 0.0,
+            clearcoat_normal_texture_index: None,
+            clearcoat_normal_texture_transform: Default::default(),
+            sheen_color_factor: [0.0; 3],
+            sheen_roughness_factor: 0.0,
+            specular_factor: 1.0,
+            specular_color_factor: [1.0; 3],
         }
     }
 }
@@ -97,6 +97,7 @@ impl EguiPaint {
             vk::SamplerAddressMode::CLAMP_TO_EDGE,
             vk::SamplerAddressMode::CLAMP_TO_EDGE,
             vk::SamplerMipmapMode::LINEAR,
+            false,
         )?;
 
         let cmd_bufs = (0..num_images)
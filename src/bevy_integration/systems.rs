@@ -27,7 +27,7 @@ use super::state::*;
 use super::surface;
 use crate::camera::Camera;
 use crate::error::{ErrorSource, SrResult};
-use crate::{Renderer, SwapchainFrame};
+use crate::{Renderer, SceneLoad, SwapchainFrame};
 
 // ---------------------------------------------------------------------------
 // Extract (main world -> render world)
@@ -159,7 +159,7 @@ fn ensure_renderer_impl(state: &mut SunrayRenderState, windows: &SunrayWindows,
         }
         if let Some(path) = scene.gltf_path.clone() {
             match state.renderer.as_mut().unwrap().load_gltf(&path) {
-                Ok((group, instances)) => {
+                Ok(SceneLoad { group, instances, .. }) => {
                     log::info!("sunray: loaded {} unique BLASes from {path}", instances.len());
                     state.scene_group = Some(group);
                     state.scene_blas_keys = instances.iter().map(|(key, _)| *key).collect();
@@ -1,10 +1,24 @@
 use nalgebra as na;
 
+/// How the camera maps view space to clip space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// Pinhole camera; the vertical field of view is [`Camera::fov_y`].
+    Perspective,
+    /// Parallel projection. `y_mag` is half the height of the view volume in
+    /// world units (glTF's `ymag`); the width follows the image aspect ratio.
+    Orthographic { y_mag: f32 },
+}
+
 #[derive(Clone, Copy)]
 pub struct Camera {
     position: na::Point3<f32>,
     target: na::Point3<f32>,
+    up: na::Vector3<f32>,
     fov_y: f32,
+    projection: Projection,
+    z_near: f32,
+    z_far: f32,
 }
 
 impl Default for Camera {
@@ -12,7 +26,11 @@ impl Default for Camera {
         Self {
             position: na::point![0.0, 0.0, 1.0],
             target: na::point![0.0, 0.0, 0.0],
+            up: na::vector![0.0, 1.0, 0.0],
             fov_y: 45.0,
+            projection: Projection::Perspective,
+            z_near: 0.1,  //render everything after this distance
+            z_far: 100.0, //discard everything after this distance
         }
     }
 }
@@ -27,26 +45,51 @@ pub(crate) struct CameraMatrices {
 
 impl Camera {
     pub fn new(position: na::Point3<f32>, target: na::Point3<f32>, fov_y: f32) -> Self {
-        Self { position, target, fov_y }
+        Self {
+            position,
+            target,
+            fov_y,
+            ..Default::default()
+        }
+    }
+
+    /// Build a camera from a world transform, glTF style: the camera sits at
+    /// the transform's origin, looks down its local `-Z` and has its local `+Y`
+    /// as up. Scale in `transform` is ignored.
+    pub fn from_transform(transform: &na::Matrix4<f32>, projection: Projection) -> Self {
+        let position = transform.transform_point(&na::Point3::origin());
+        let forward = transform.transform_vector(&-na::Vector3::z()).normalize();
+        let up = transform.transform_vector(&na::Vector3::y()).normalize();
+
+        Self {
+            position,
+            target: position + forward,
+            up,
+            projection,
+            ..Default::default()
+        }
     }
 
     pub(crate) fn as_matrices(&self, extent: ash::vk::Extent3D) -> CameraMatrices {
         let eye = self.position;
         let target = self.target;
-        let up = &na::vector![0.0, 1.0, 0.0];
+        let aspect = extent.width as f32 / extent.height as f32;
 
         //view-space: camera pov
-        let view = na::Isometry3::look_at_rh(&eye, &target, up);
+        let view = na::Isometry3::look_at_rh(&eye, &target, &self.up);
         //clip_space: normalised coordinates adding perspective
-        let projection = na::Perspective3::new(
-            extent.width as f32 / extent.height as f32,
-            self.fov_y.to_radians(),
-            0.1,   //render everything after this distance
-            100.0, //discard everything after this distance
-        );
+        let projection = match self.projection {
+            Projection::Perspective => {
+                na::Perspective3::new(aspect, self.fov_y.to_radians(), self.z_near, self.z_far).to_homogeneous()
+            }
+            Projection::Orthographic { y_mag } => {
+                let x_mag = y_mag * aspect;
+                na::Orthographic3::new(-x_mag, x_mag, -y_mag, y_mag, self.z_near, self.z_far).to_homogeneous()
+            }
+        };
 
         let view_homogeneous = view.to_homogeneous();
-        let mut proj_homogeneous = projection.to_homogeneous();
+        let mut proj_homogeneous = projection;
 
         proj_homogeneous[(1, 1)] *= -1.0;
 
@@ -70,10 +113,26 @@ impl Camera {
         self.target
     }
 
+    pub fn up(&self) -> na::Vector3<f32> {
+        self.up
+    }
+
     pub fn fov_y(&self) -> f32 {
         self.fov_y
     }
 
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    pub fn z_near(&self) -> f32 {
+        self.z_near
+    }
+
+    pub fn z_far(&self) -> f32 {
+        self.z_far
+    }
+
     pub fn set_position(mut self, position: na::Point3<f32>) -> Self {
         self.position = position;
 
@@ -86,9 +145,28 @@ impl Camera {
         self
     }
 
+    pub fn set_up(mut self, up: na::Vector3<f32>) -> Self {
+        self.up = up;
+
+        self
+    }
+
     pub fn set_fov_y(mut self, fov: f32) -> Self {
         self.fov_y = fov;
 
         self
     }
+
+    pub fn set_projection(mut self, projection: Projection) -> Self {
+        self.projection = projection;
+
+        self
+    }
+
+    pub fn set_clip_planes(mut self, z_near: f32, z_far: f32) -> Self {
+        self.z_near = z_near;
+        self.z_far = z_far;
+
+        self
+    }
 }
@@ -0,0 +1,432 @@
+use std::rc::Rc;
+
+use ash::vk;
+
+use crate::MAX_FRAMES_IN_FLIGHT;
+use crate::error::{SrError, SrResult};
+use crate::vulkan_abstraction::gltf::PunctualLight;
+use crate::vulkan_abstraction::{self, Buffer, HostAccessibleBuffer};
+
+/// What rays leaving the scene see. The environment is also a light source:
+/// NEE and ReSTIR sample it like the emissive triangles and punctual lights,
+/// importance-sampling maps by luminance.
+#[derive(Clone, Debug, Default)]
+pub enum Environment {
+    /// Black sky: only emissive geometry and punctual lights light the scene.
+    #[default]
+    None,
+    /// The same radiance in every direction.
+    Constant([f32; 3]),
+    /// Blends from `horizon` up to `zenith` above the horizon and down to
+    /// `ground` below it.
+    Gradient {
+        zenith: [f32; 3],
+        horizon: [f32; 3],
+        ground: [f32; 3],
+    },
+    Map(EnvironmentMap),
+    /// Analytic daylight sky with a sun, see [`Sky`].
+    Sky(Sky),
+}
+
+/// Equirectangular (latitude-longitude) radiance map. `+Y` is up and the
+/// center of the image looks down `-Z`.
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    pub width: u32,
+    pub height: u32,
+    /// Linear RGBA radiance, row-major, top row first. Alpha is ignored.
+    pub pixels: Vec<[f32; 4]>,
+    /// Scale applied to every pixel.
+    pub intensity: f32,
+    /// Rotation around `+Y`, in degrees.
+    pub rotation: f32,
+}
+
+impl EnvironmentMap {
+    /// Load an HDR image (.hdr, .exr, or anything else the `image` crate can
+    /// decode; LDR images are taken as linear).
+    pub fn from_file(path: &str) -> SrResult<Self> {
+        let image = image::open(path)
+            .map_err(|e| SrError::new_custom(format!("could not load environment map '{path}': {e}")))?
+            .into_rgba32f();
+        let (width, height) = image.dimensions();
+        let pixels = image.pixels().map(|pixel| pixel.0).collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+            intensity: 1.0,
+            rotation: 0.0,
+        })
+    }
+
+    /// Sampling distribution of the map, in the layout `shaders/rt_utils.slang`
+    /// reads: `height` entries of marginal CDF over rows, then one conditional
+    /// CDF of `width` entries per row. Both are inclusive and normalized, so a
+    /// texel's probability is the product of its two CDF steps. Texels are
+    /// weighted by luminance times `sin(theta)`, the solid angle they cover.
+    fn sampling_cdf(&self) -> Vec<f32> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut cdf = vec![0.0; height + width * height];
+        let (marginal, conditional) = cdf.split_at_mut(height);
+
+        let mut total = 0.0;
+        for (y, row_cdf) in conditional.chunks_exact_mut(width).enumerate() {
+            let sin_theta = ((y as f32 + 0.5) / height as f32 * std::f32::consts::PI).sin();
+            let mut row_sum = 0.0;
+            for (x, entry) in row_cdf.iter_mut().enumerate() {
+                let [r, g, b, _] = self.pixels[y * width + x];
+                row_sum += (0.2126 * r + 0.7152 * g + 0.0722 * b).max(0.0) * sin_theta;
+                *entry = row_sum;
+            }
+            // a black row is never picked by the marginal, but keep its CDF valid
+            for (x, entry) in row_cdf.iter_mut().enumerate() {
+                *entry = if row_sum > 0.0 {
+                    *entry / row_sum
+                } else {
+                    (x + 1) as f32 / width as f32
+                };
+            }
+            total += row_sum;
+            marginal[y] = total;
+        }
+        for (y, entry) in marginal.iter_mut().enumerate() {
+            *entry = if total > 0.0 {
+                *entry / total
+            } else {
+                (y + 1) as f32 / height as f32
+            };
+        }
+
+        cdf
+    }
+}
+
+/// Preetham et al. analytic daylight sky ("A Practical Analytic Model for
+/// Daylight", 1999) with a sun disc. Besides being drawn, the sun lights the
+/// scene as a directional light, attenuated by the atmosphere it crosses.
+#[derive(Clone, Copy, Debug)]
+pub struct Sky {
+    /// Direction toward the sun, `+Y` up (normalized on use). See
+    /// [`Sky::sun_direction_from_angles`]. The sky fades to black while the
+    /// sun sets below the horizon.
+    pub sun_direction: [f32; 3],
+    /// Haziness of the atmosphere: 2 is a very clear sky, 10 a hazy one (the
+    /// range the model is fitted for).
+    pub turbidity: f32,
+    /// Scale of the sky radiance, which the model gives in kcd/m².
+    pub sky_intensity: f32,
+    /// Illuminance of the sun on a surface facing it, before the atmosphere
+    /// attenuates it, in the units of a directional light's intensity.
+    pub sun_intensity: f32,
+}
+
+impl Default for Sky {
+    fn default() -> Self {
+        Self {
+            sun_direction: Self::sun_direction_from_angles(45.0, 30.0),
+            turbidity: 3.0,
+            sky_intensity: 0.1,
+            sun_intensity: 10.0,
+        }
+    }
+}
+
+impl Sky {
+    /// Angular radius of the sun disc, in radians.
+    const SUN_ANGULAR_RADIUS: f32 = 0.004_65;
+
+    /// Direction toward a sun `elevation` degrees above the horizon and
+    /// `azimuth` degrees around `+Y`, from `-Z` toward `+X`.
+    pub fn sun_direction_from_angles(elevation: f32, azimuth: f32) -> [f32; 3] {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        [
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        ]
+    }
+
+    /// Perez distribution coefficients A..E, each for the (Y, x, y) channels.
+    fn perez_coefficients(&self) -> [[f32; 3]; 5] {
+        let t = self.turbidity;
+        [
+            [0.1787 * t - 1.4630, -0.0193 * t - 0.2592, -0.0167 * t - 0.2608],
+            [-0.3554 * t + 0.4275, -0.0665 * t + 0.0008, -0.0950 * t + 0.0092],
+            [-0.0227 * t + 5.3251, -0.0004 * t + 0.2125, -0.0079 * t + 0.2102],
+            [0.1206 * t - 2.5771, -0.0641 * t - 0.8989, -0.0441 * t - 1.6537],
+            [-0.0670 * t + 0.3703, -0.0033 * t + 0.0452, -0.0109 * t + 0.0529],
+        ]
+    }
+
+    /// Luminance (kcd/m²) and chromaticity of the zenith, for a sun
+    /// `theta_s` radians from it.
+    fn zenith_yxy(&self, theta_s: f32) -> [f32; 3] {
+        let t = self.turbidity;
+        let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let th = [theta_s.powi(3), theta_s.powi(2), theta_s, 1.0];
+        let dot = |c: [f32; 4]| c.iter().zip(th).map(|(c, th)| c * th).sum::<f32>();
+        let x = t * t * dot([0.00166, -0.00375, 0.00209, 0.0])
+            + t * dot([-0.02903, 0.06377, -0.03202, 0.00394])
+            + dot([0.11693, -0.21196, 0.06052, 0.25886]);
+        let y = t * t * dot([0.00275, -0.00610, 0.00317, 0.0])
+            + t * dot([-0.04214, 0.08970, -0.04153, 0.00516])
+            + dot([0.15346, -0.26756, 0.06670, 0.26688]);
+
+        [luminance, x, y]
+    }
+
+    /// Fraction of sunlight (at the red, green and blue wavelengths) that
+    /// crosses the atmosphere: Rayleigh and aerosol extinction over the
+    /// relative air mass of a sun with zenith cosine `cos_theta_s`.
+    fn sun_transmittance(&self, cos_theta_s: f32) -> [f32; 3] {
+        let theta_degrees = cos_theta_s.acos().to_degrees();
+        let air_mass = 1.0 / (cos_theta_s + 0.15 * (93.885 - theta_degrees).powf(-1.253));
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        // wavelengths in micrometers
+        [0.68_f32, 0.55, 0.44].map(|lambda| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * air_mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * air_mass).exp();
+            rayleigh * aerosol
+        })
+    }
+
+    /// Shader parameters of the sky, and the sun as a directional light
+    /// (`None` once it has set).
+    fn gpu_data(&self) -> (EnvironmentGpuData, Option<PunctualLight>) {
+        let [x, y, z] = self.sun_direction;
+        let length = (x * x + y * y + z * z).sqrt().max(f32::MIN_POSITIVE);
+        let sun_direction = [x / length, y / length, z / length];
+
+        // The model is only valid for a sun above the horizon: clamp it there
+        // and fade the sky out over the first few degrees below.
+        let cos_theta_s = sun_direction[1].max(0.0);
+        let theta_s = cos_theta_s.acos();
+        let twilight = ((sun_direction[1] + 0.1) / 0.1).clamp(0.0, 1.0);
+
+        let coefficients = self.perez_coefficients();
+        let zenith = self.zenith_yxy(theta_s);
+        // Perez F(theta = 0, gamma = theta_s), the normalization to the zenith
+        let mut zenith_scale = [0.0; 3];
+        for (channel, scale) in zenith_scale.iter_mut().enumerate() {
+            let [a, b, c, d, e] = coefficients.map(|coefficient| coefficient[channel]);
+            let f_zenith = (1.0 + a * b.exp()) * (1.0 + c * (d * theta_s).exp() + e * cos_theta_s * cos_theta_s);
+            *scale = zenith[channel] / f_zenith;
+        }
+
+        let sun = (sun_direction[1] > 0.0).then(|| {
+            let transmittance = self.sun_transmittance(cos_theta_s);
+            PunctualLight {
+                kind: PunctualLight::DIRECTIONAL,
+                direction: sun_direction.map(|c| -c),
+                intensity: transmittance.map(|t| t * self.sun_intensity),
+                ..Default::default()
+            }
+        });
+        // radiance of the disc such that it integrates to the light's illuminance
+        let half_radius_sin = (Self::SUN_ANGULAR_RADIUS / 2.0).sin();
+        let sun_solid_angle = 4.0 * std::f32::consts::PI * half_radius_sin * half_radius_sin;
+        let sun_radiance = sun.map_or([0.0; 3], |sun| sun.intensity.map(|i| i / sun_solid_angle));
+
+        let rgba = |c: [f32; 3], w: f32| [c[0], c[1], c[2], w];
+        let data = EnvironmentGpuData {
+            kind: EnvironmentGpuData::SKY,
+            intensity: self.sky_intensity * twilight,
+            perez_a: rgba(coefficients[0], 0.0),
+            perez_b: rgba(coefficients[1], 0.0),
+            perez_c: rgba(coefficients[2], 0.0),
+            perez_d: rgba(coefficients[3], 0.0),
+            perez_e: rgba(coefficients[4], 0.0),
+            sky_zenith: rgba(zenith_scale, 0.0),
+            sun_direction: rgba(sun_direction, Self::SUN_ANGULAR_RADIUS.cos()),
+            sun_radiance: rgba(sun_radiance, 0.0),
+            ..Default::default()
+        };
+
+        (data, sun)
+    }
+}
+
+/// GPU mirror of `EnvironmentInfo` in `shaders/rt_types.slang` (208 bytes,
+/// std430), reached by the shaders through a device address.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct EnvironmentGpuData {
+    /// Constant color, or the gradient's zenith color.
+    pub color0: [f32; 4],
+    /// Gradient horizon color.
+    pub color1: [f32; 4],
+    /// Gradient ground color.
+    pub color2: [f32; 4],
+    pub kind: u32,
+    /// Heap slots of the map image and sampler.
+    pub image: u32,
+    pub sampler: u32,
+    pub width: u32,
+    pub height: u32,
+    pub intensity: f32,
+    /// Rotation around `+Y`, in radians.
+    pub rotation: f32,
+    pub _padding: u32,
+    /// Sky Perez coefficients A..E for (Y, x, y).
+    pub perez_a: [f32; 4],
+    pub perez_b: [f32; 4],
+    pub perez_c: [f32; 4],
+    pub perez_d: [f32; 4],
+    pub perez_e: [f32; 4],
+    /// Sky zenith (Y, x, y) over the Perez function at the zenith.
+    pub sky_zenith: [f32; 4],
+    /// Direction toward the sun; `w` is the cosine of the disc's angular radius.
+    pub sun_direction: [f32; 4],
+    /// Radiance of the sun disc (zero once it has set).
+    pub sun_radiance: [f32; 4],
+}
+
+impl EnvironmentGpuData {
+    pub const NONE: u32 = 0;
+    pub const CONSTANT: u32 = 1;
+    pub const GRADIENT: u32 = 2;
+    pub const MAP: u32 = 3;
+    pub const SKY: u32 = 4;
+}
+
+/// GPU resources of the current [`Environment`]: its parameters, the map's
+/// sampling CDF (a single dummy entry when there's no map) and the map image
+/// itself. The parameters are written into a per-frame-in-flight pool (like
+/// the camera matrices) so that a sky can be changed every frame without
+/// waiting for the GPU.
+pub(crate) struct EnvironmentResources {
+    data: EnvironmentGpuData,
+    /// The sky's sun, added to the frame's punctual lights.
+    sun: Option<PunctualLight>,
+    info_pool: Vec<vulkan_abstraction::UniformBuffer<EnvironmentGpuData>>,
+    cdf: vulkan_abstraction::GpuOnlyBuffer,
+    _image: Option<vulkan_abstraction::Image>,
+    _sampler: Option<vulkan_abstraction::Sampler>,
+}
+
+impl EnvironmentResources {
+    pub fn new(core: Rc<vulkan_abstraction::Core>, environment: &Environment) -> SrResult<Self> {
+        let rgba = |c: &[f32; 3]| [c[0], c[1], c[2], 0.0];
+        let mut data = EnvironmentGpuData {
+            intensity: 1.0,
+            ..Default::default()
+        };
+        let mut sun = None;
+        let mut cdf = vec![0.0];
+        let mut image = None;
+        let mut sampler = None;
+
+        match environment {
+            Environment::None => data.kind = EnvironmentGpuData::NONE,
+            Environment::Constant(color) => {
+                data.kind = EnvironmentGpuData::CONSTANT;
+                data.color0 = rgba(color);
+            }
+            Environment::Gradient { zenith, horizon, ground } => {
+                data.kind = EnvironmentGpuData::GRADIENT;
+                data.color0 = rgba(zenith);
+                data.color1 = rgba(horizon);
+                data.color2 = rgba(ground);
+            }
+            Environment::Sky(sky) => (data, sun) = sky.gpu_data(),
+            Environment::Map(map) => {
+                if map.width == 0 || map.height == 0 || map.pixels.len() != (map.width * map.height) as usize {
+                    return Err(SrError::new_custom(format!(
+                        "environment map: {} pixels do not make a {}x{} image",
+                        map.pixels.len(),
+                        map.width,
+                        map.height
+                    )));
+                }
+
+                let map_image = vulkan_abstraction::Image::new_from_data(
+                    Rc::clone(&core),
+                    bytemuck::cast_slice(&map.pixels).to_vec(),
+                    vk::Extent3D {
+                        width: map.width,
+                        height: map.height,
+                        depth: 1,
+                    },
+                    vk::Format::R32G32B32A32_SFLOAT,
+                    vk::ImageTiling::OPTIMAL,
+                    gpu_allocator::MemoryLocation::GpuOnly,
+                    vk::ImageUsageFlags::SAMPLED,
+                    "environment map",
+                )?;
+                // NEAREST: linear filtering of 32-bit float images is an optional
+                // format feature, and at HDRI resolutions it's barely visible.
+                let map_sampler = vulkan_abstraction::Sampler::new(
+                    Rc::clone(&core),
+                    vk::Filter::NEAREST,
+                    vk::Filter::NEAREST,
+                    vk::SamplerAddressMode::REPEAT,
+                    vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    vk::SamplerAddressMode::CLAMP_TO_EDGE,
+                    vk::SamplerMipmapMode::NEAREST,
+                    false,
+                )?;
+
+                data.kind = EnvironmentGpuData::MAP;
+                data.image = map_image.sampled_slot();
+                data.sampler = map_sampler.slot();
+                data.width = map.width;
+                data.height = map.height;
+                data.intensity = map.intensity;
+                data.rotation = map.rotation.to_radians();
+                cdf = map.sampling_cdf();
+                image = Some(map_image);
+                sampler = Some(map_sampler);
+            }
+        }
+
+        let info_pool = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| vulkan_abstraction::UniformBuffer::new(Rc::clone(&core), 1))
+            .collect::<SrResult<Vec<_>>>()?;
+        let cdf = vulkan_abstraction::GpuOnlyBuffer::new_from_data(
+            core,
+            &cdf,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            "environment sampling cdf",
+        )?;
+
+        Ok(Self {
+            data,
+            sun,
+            info_pool,
+            cdf,
+            _image: image,
+            _sampler: sampler,
+        })
+    }
+
+    /// Change the sky in place, without touching GPU resources. Returns
+    /// `false` (changing nothing) if the environment isn't a sky.
+    pub fn update_sky(&mut self, sky: &Sky) -> bool {
+        if self.data.kind != EnvironmentGpuData::SKY {
+            return false;
+        }
+        (self.data, self.sun) = sky.gpu_data();
+        true
+    }
+
+    /// Write the parameters into the pool's `slot` (a frame-in-flight index,
+    /// already free for reuse) and return its device address.
+    pub fn upload(&mut self, slot: usize) -> SrResult<vk::DeviceAddress> {
+        let info = &mut self.info_pool[slot];
+        info.map_mut()?[0] = self.data;
+        Ok(info.get_device_address())
+    }
+
+    pub fn sun_light(&self) -> Option<PunctualLight> {
+        self.sun
+    }
+
+    pub fn cdf_slot(&self) -> u32 {
+        self.cdf.raw().storage_slot()
+    }
+}
@@ -1,8 +1,11 @@
 pub mod camera;
+pub mod environment;
 pub mod error;
 pub mod finello_pathtracing_pipeline;
 pub mod render_graph;
 pub mod scene;
+pub mod scene_graph;
+pub mod scene_loading;
 pub mod shader_compiler;
 pub mod utils;
 pub mod vulkan_abstraction;
@@ -16,9 +19,13 @@ pub mod vulkan_abstraction;
 pub mod bevy_integration;
 
 pub use crate::vulkan_abstraction::DiagnosticTool;
+pub use crate::vulkan_abstraction::{DEFAULT_TEXTURE_BUDGET, RESIDENT_TAIL_EXTENT};
 pub use camera::*;
+pub use environment::{Environment, EnvironmentMap, Sky};
 use error::*;
 pub use scene::*;
+pub use scene_graph::*;
+pub use scene_loading::*;
 
 use std::hash::Hash;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
@@ -57,6 +64,152 @@ pub struct ResourceKey {
     pub index: u64,
 }
 
+/// What [`Renderer::render`] draws.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderMode {
+    /// The path-traced image, accumulated, denoised and tonemapped.
+    #[default]
+    PathTraced,
+    /// Ambient occlusion only, for asset review: how much of the hemisphere
+    /// above each visible surface is open within `radius` (in scene units),
+    /// times the material's occlusion texture, from black to white. Replaces
+    /// every pass of the path tracer with a single trace.
+    AmbientOcclusion { radius: f32 },
+}
+
+/// What a scene load hands back to the caller: the asset group (usable with
+/// [`Renderer::unload_scene`]), the instance list in the
+/// `(blas key, world transforms)` form [`Renderer::render`] takes along with
+/// the glTF node of each instance, the scene's node hierarchy, the cameras
+/// authored in the scene, the skins, the instances deformed by skins or morph
+/// targets, and the primitives that couldn't be loaded (points and lines).
+pub struct SceneLoad<K> {
+    pub group: u64,
+    pub instances: Vec<(K, Vec<vk::TransformMatrixKHR>)>,
+    /// The glTF node of each transform in `instances`, laid out the same way;
+    /// see [`vulkan_abstraction::gltf::SceneAnimation::instances`].
+    pub instance_nodes: Vec<Vec<usize>>,
+    /// The scene's nodes with their names, parents and local transforms; see
+    /// [`SceneGraph::instances`].
+    pub graph: SceneGraph,
+    pub cameras: Vec<Camera>,
+    /// The glTF document's skins, indexed by [`DeformedInstance::skin`].
+    pub skins: Vec<vulkan_abstraction::gltf::Skin>,
+    /// Every instance deformed by a skin or morph targets. Each has a BLAS of
+    /// its own, also listed in `instances`, posed with
+    /// [`Renderer::set_joint_matrices`] and [`Renderer::set_morph_weights`].
+    pub deformed_instances: Vec<DeformedInstance<K>>,
+    pub skipped_primitives: Vec<vulkan_abstraction::gltf::SkippedPrimitive>,
+}
+
+impl<K: Copy> SceneLoad<K> {
+    /// `instances` with every transform replaced by its node's entry in
+    /// `world_transforms` (glTF node index → world transform). Nodes missing
+    /// from the map keep their transform.
+    pub fn posed_instances(
+        &self,
+        world_transforms: &HashMap<usize, nalgebra::Matrix4<f32>>,
+    ) -> Vec<(K, Vec<vk::TransformMatrixKHR>)> {
+        self.instances
+            .iter()
+            .zip(&self.instance_nodes)
+            .map(|((key, transforms), nodes)| {
+                let transforms = transforms
+                    .iter()
+                    .zip(nodes)
+                    .map(|(transform, node)| match world_transforms.get(node) {
+                        Some(world) => utils::na_mat4_to_vk_transform(*world),
+                        None => *transform,
+                    })
+                    .collect();
+                (*key, transforms)
+            })
+            .collect()
+    }
+
+    /// The joint matrices of every skinned instance for the nodes'
+    /// `world_transforms`, ready for [`Renderer::set_joint_matrices`].
+    pub fn posed_joint_matrices(
+        &self,
+        world_transforms: &HashMap<usize, nalgebra::Matrix4<f32>>,
+    ) -> Vec<(K, Vec<nalgebra::Matrix4<f32>>)> {
+        let world = |node: usize| {
+            world_transforms
+                .get(&node)
+                .copied()
+                .unwrap_or_else(nalgebra::Matrix4::identity)
+        };
+
+        self.deformed_instances
+            .iter()
+            .filter_map(|instance| {
+                let skin = &self.skins[instance.skin?];
+                Some((instance.key, skin.joint_matrices(&world(instance.node), world)))
+            })
+            .collect()
+    }
+}
+
+/// Group the `(blas index, transform)` instances of a scene and the nodes
+/// instancing them, parallel, by the BLAS keys `blas_keys` (indexed by BLAS
+/// index), preserving order: the [`SceneLoad::instances`] and
+/// [`SceneLoad::instance_nodes`] of the scene's static BLASes.
+fn group_instances<K: Copy>(
+    blas_keys: &[K],
+    instances: impl IntoIterator<Item = (usize, vk::TransformMatrixKHR)>,
+    instance_nodes: impl IntoIterator<Item = usize>,
+) -> (Vec<(K, Vec<vk::TransformMatrixKHR>)>, Vec<Vec<usize>>) {
+    let mut grouped: Vec<(K, Vec<vk::TransformMatrixKHR>)> = blas_keys.iter().map(|&k| (k, Vec::new())).collect();
+    let mut grouped_nodes: Vec<Vec<usize>> = vec![Vec::new(); blas_keys.len()];
+    for ((blas_index, transform), node) in instances.into_iter().zip(instance_nodes) {
+        grouped[blas_index].1.push(transform);
+        grouped_nodes[blas_index].push(node);
+    }
+    (grouped, grouped_nodes)
+}
+
+#[cfg(test)]
+impl SceneLoad<usize> {
+    /// What loading `scene` returns, without a GPU: the BLASes are keyed by
+    /// their index in the scene's layout, and the deformed instances are left
+    /// out.
+    pub(crate) fn for_test(scene: &Scene) -> Self {
+        let layout = scene.layout();
+        let blas_keys: Vec<usize> = (0..layout.primitives.len()).collect();
+        let (instances, instance_nodes) = group_instances(
+            &blas_keys,
+            layout.instances.iter().copied(),
+            layout.instance_nodes.iter().copied(),
+        );
+
+        Self {
+            group: 0,
+            instances,
+            instance_nodes,
+            graph: scene.graph(),
+            cameras: vec![],
+            skins: scene.skins().to_vec(),
+            deformed_instances: vec![],
+            skipped_primitives: vec![],
+        }
+    }
+}
+
+/// A deformed instance of a loaded scene: the key of its BLAS, the glTF node
+/// instancing it, the skin deforming it and how many morph targets it has.
+#[derive(Clone, Copy, Debug)]
+pub struct DeformedInstance<K> {
+    pub key: K,
+    /// glTF index of the node.
+    pub node: usize,
+    /// Index into [`SceneLoad::skins`], `None` when only morph targets deform
+    /// the instance.
+    pub skin: Option<usize>,
+    /// The number of weights [`Renderer::set_morph_weights`] takes, zero
+    /// without morph targets.
+    pub morph_target_count: usize,
+}
+
 /// The number of concurrent frames that are processed (both by CPU and GPU).
 ///
 /// Apparently 2 is the most common choice. Empirically it seems like the performance doesn't really
@@ -104,6 +257,8 @@ pub struct Renderer<K: Hash + Eq + Copy + 'static = ResourceKey> {
     ray_gen_ris_spirv: &'static [u8],
     /// Ray-gen for the final pass: traces rays based on the reservoirs the RIS pass produced.
     ray_gen_final_spirv: &'static [u8],
+    /// Ray-gen of [`RenderMode::AmbientOcclusion`], which it renders on its own.
+    ray_gen_ao_spirv: &'static [u8],
 
     ray_miss_spirv: &'static [u8],
     closest_hit_spirv: &'static [u8],
@@ -114,6 +269,12 @@ pub struct Renderer<K: Hash + Eq + Copy + 'static = ResourceKey> {
     denoise_spirv: &'static [u8],
     ///An extra pass to handle post-processing like exposure and color correction. Should be mathematically easy to calculate
     postprocess_spirv: &'static [u8],
+    ///Deforms skinned meshes into their BLAS vertex buffers before the BLASes are refit
+    skinning_spirv: &'static [u8],
+    ///Blends the morph targets of morphed meshes into their BLAS vertex buffers (or skinning rest poses)
+    morph_spirv: &'static [u8],
+
+    render_mode: RenderMode,
 
     // this is about the frame being worked on by the cpu
     image_extent: vk::Extent3D,
@@ -122,6 +283,10 @@ pub struct Renderer<K: Hash + Eq + Copy + 'static = ResourceKey> {
     blue_noise_image: vulkan_abstraction::Image,
     blue_noise_sampler: vulkan_abstraction::Sampler,
 
+    /// GPU side of the [`Environment`] set with [`Self::set_environment`]:
+    /// seen by rays that leave the scene and sampled as a light.
+    environment: environment::EnvironmentResources,
+
     core: Rc<vulkan_abstraction::Core>,
 
     //TODO finni all of this params are pipeline-specific temporal (cross-frame) stuff. They now
@@ -199,12 +364,20 @@ pub struct Renderer<K: Hash + Eq + Copy + 'static = ResourceKey> {
 
 /// Per-frame GPU inputs of the unified graph that live in frame-local buffers
 /// (created on the spot in `render`, deferred-freed via the end-of-frame
-/// callbacks): the camera matrices UBO address and the heap slots of the flat
-/// transform / emissive indirection buffers.
+/// callbacks): the camera matrices and environment UBO addresses, the heap slots of the flat
+/// transform / emissive indirection / punctual light buffers, and the light
+/// counts (the buffers are padded, so their size can't be used for that).
 struct FrameGpuData {
     matrices_address: vk::DeviceAddress,
+    environment_address: vk::DeviceAddress,
     entity_transforms_slot: u32,
     emissive_indirection_slot: u32,
+    punctual_lights_slot: u32,
+    emissive_light_count: u32,
+    punctual_light_count: u32,
+    /// The texture streaming feedback buffer of the frame's slot, which the
+    /// resource manager keeps across frames.
+    texture_feedback_slot: u32,
 }
 // `K: 'static` propagated from `ResourceManager` (its deferred frame work is
 // stored as boxed callbacks).
@@ -265,12 +438,15 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
         let ray_gen_ris_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/ray_gen_ris.spirv"));
         let ray_gen_final_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/ray_gen_final.spirv"));
+        let ray_gen_ao_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/ray_gen_ao.spirv"));
         let ray_miss_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/ray_miss.spirv"));
         let closest_hit_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/closest_hit.spirv"));
         let any_hit_spirv: &'static [u8] = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/any_hit.spirv"));
         let denoise_spirv = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/denoise.spirv"));
         let postprocess_spirv = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/postprocess.spirv"));
         let temporal_accumulation_spirv = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/temporal_accumulation.spirv"));
+        let skinning_spirv = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/skinning.spirv"));
+        let morph_spirv = include_bytes_align_as!(u32, concat!(env!("OUT_DIR"), "/morph.spirv"));
 
         let image_dependant_data = HashMap::new();
 
@@ -306,8 +482,11 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             vk::SamplerAddressMode::REPEAT,
             vk::SamplerAddressMode::REPEAT,
             vk::SamplerMipmapMode::NEAREST,
+            false,
         )?;
 
+        let environment = environment::EnvironmentResources::new(Rc::clone(&core), &Environment::None)?;
+
         let mut render_graph = RenderGraph::new(Rc::clone(&core))?;
 
         // Per-slot camera-matrices UBOs (stable device addresses; see field doc).
@@ -400,16 +579,21 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
             ray_gen_ris_spirv,
             ray_gen_final_spirv,
+            ray_gen_ao_spirv,
             ray_miss_spirv,
             closest_hit_spirv,
             any_hit_spirv,
             denoise_spirv,
             temporal_accumulation_spirv,
             postprocess_spirv,
+            skinning_spirv,
+            morph_spirv,
 
             prev_view_proj: nalgebra::zero(),
             matrices_pool,
 
+            render_mode: RenderMode::default(),
+
             image_extent,
             image_format,
 
@@ -419,6 +603,7 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
             blue_noise_image,
             blue_noise_sampler,
+            environment,
 
             resource_manager,
             reservoir_gi_temporal,
@@ -776,39 +961,171 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
     /// Load a glTF file's default scene. See [`Self::load_scene`] for the
     /// return contract.
-    pub fn load_gltf(&mut self, path: &str) -> SrResult<(u64, Vec<(K, Vec<vk::TransformMatrixKHR>)>)>
+    pub fn load_gltf(&mut self, path: &str) -> SrResult<SceneLoad<K>>
+    where
+        K: From<ResourceKey>,
+    {
+        let gltf = vulkan_abstraction::gltf::Gltf::new(path)?;
+        let (default_scene, scene_data) = gltf.create_default_scene()?;
+        self.load_scene(&default_scene, scene_data)
+    }
+
+    /// Load scenes of a glTF document opened with
+    /// [`vulkan_abstraction::gltf::Gltf::new`] or
+    /// [`vulkan_abstraction::gltf::Gltf::from_slice`] (whose `scenes` lists
+    /// them). Each scene gets its own asset group: the returned loads, in
+    /// `scenes` order, can be unloaded independently with
+    /// [`Self::unload_scene`]. If a scene fails to load, the ones loaded before
+    /// it are unloaded again.
+    pub fn load_gltf_scenes(
+        &mut self,
+        gltf: &vulkan_abstraction::gltf::Gltf,
+        scenes: &[vulkan_abstraction::gltf::SceneSelector],
+    ) -> SrResult<Vec<SceneLoad<K>>>
+    where
+        K: From<ResourceKey>,
+    {
+        let mut loads = Vec::with_capacity(scenes.len());
+        for selector in scenes {
+            let load = gltf
+                .scene_index(selector)
+                .and_then(|index| gltf.create_scene(index))
+                .and_then(|(scene, scene_data)| self.load_scene(&scene, scene_data));
+
+            match load {
+                Ok(load) => loads.push(load),
+                Err(e) => {
+                    for load in loads {
+                        self.unload_scene(load.group)?;
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        Ok(loads)
+    }
+
+    /// Load the default scene of a glTF document held in memory: a .glb, or a
+    /// .gltf whose external buffers and images `resolver` supplies (see
+    /// [`vulkan_abstraction::gltf::UriResolver`]; self-contained documents can
+    /// pass [`vulkan_abstraction::gltf::EmbeddedOnly`]). See
+    /// [`Self::load_scene`] for the return contract.
+    pub fn load_gltf_from_slice(
+        &mut self,
+        bytes: &[u8],
+        resolver: &dyn vulkan_abstraction::gltf::UriResolver,
+    ) -> SrResult<SceneLoad<K>>
     where
         K: From<ResourceKey>,
     {
-        let gltf = vulkan_abstraction::gltf::Gltf::new(Rc::clone(&self.core), path)?;
+        let gltf = vulkan_abstraction::gltf::Gltf::from_slice(bytes, resolver)?;
         let (default_scene, scene_data) = gltf.create_default_scene()?;
         self.load_scene(&default_scene, scene_data)
     }
 
     /// Load a scene's assets into the resource manager. Returns the asset
     /// group index (usable with [`Self::unload_scene`] to free everything this
-    /// call created in bulk) and the scene's instances as the
-    /// `(blas key, world transforms)` vector. The instance list is *not*
-    /// retained anywhere — the caller owns it, mutates it, and passes it to
-    /// [`Self::render`] / [`Self::render_to_swapchain`] every frame.
-    pub fn load_scene(&mut self, scene: &Scene, scene_data: SceneData) -> SrResult<(u64, Vec<(K, Vec<vk::TransformMatrixKHR>)>)>
+    /// call created in bulk), the scene's instances as the
+    /// `(blas key, world transforms)` vector, the scene's cameras, its skins
+    /// and deformed instances, and the primitives the loader skipped. The
+    /// instance list is *not* retained anywhere — the caller owns it, mutates
+    /// it, and passes it to [`Self::render`] / [`Self::render_to_swapchain`]
+    /// every frame.
+    ///
+    /// The GPU is idled and the whole scene uploaded and built before this
+    /// returns; [`Self::load_gltf_async`] loads in the background instead.
+    pub fn load_scene(&mut self, scene: &Scene, mut scene_data: SceneData) -> SrResult<SceneLoad<K>>
     where
         K: From<ResourceKey>,
     {
-        // Wait for all in-flight GPU work before invalidating descriptor sets that reference
-        // buffers which will be reallocated (e.g. emissive_indirection_gpu).
+        // Wait for all in-flight GPU work: the synchronous upload below submits outside the
+        // frame graph, and the image dependent data is rebuilt around the new scene.
         unsafe { self.core.device().inner().device_wait_idle() }?;
 
+        let skipped_primitives = std::mem::take(&mut scene_data.skipped_primitives);
+        let loaded = scene.load_into_gpu(&self.core, scene_data)?;
+        let load = self.add_loaded_scene(scene, loaded, skipped_primitives)?;
+
+        self.clear_image_dependent_data();
+        if let Some(sc) = &self.swapchain_data {
+            let images = sc.swapchain.images().to_vec();
+            self.build_image_dependent_data(&images)?;
+        }
+
+        Ok(load)
+    }
+
+    /// Start loading a glTF file's default scene in the background: a worker
+    /// thread reads the document, decodes its images and prepares their mip
+    /// levels (see [`SceneData::prepare_images`]), then
+    /// [`Self::poll_scene_load`] uploads it a little at a time.
+    pub fn load_gltf_async(&self, path: &str) -> SrResult<PendingSceneLoad> {
+        let path = path.to_string();
+        let formats = TextureFormatSupport::query(&self.core);
+        PendingSceneLoad::spawn(move || {
+            let (scene, mut scene_data) = vulkan_abstraction::gltf::Gltf::new(&path)?.create_default_scene()?;
+            scene_data.prepare_images(&formats)?;
+            Ok((scene, scene_data))
+        })
+    }
+
+    /// Move a background scene load forward; call it once per frame until it
+    /// returns [`SceneLoadStatus::Ready`]. Once the worker thread is done,
+    /// each call stages about [`SCENE_UPLOAD_BUDGET`] bytes of the scene,
+    /// without waiting for the GPU. The last one registers the scene, without
+    /// idling the GPU either, and returns the same [`SceneLoad`] as
+    /// [`Self::load_scene`]. Its images and geometry are copied by the next
+    /// frame's transfer prologue and its BLASes built by its render graph,
+    /// ahead of the TLAS, so its instances can be rendered right away.
+    pub fn poll_scene_load(&mut self, pending: &mut PendingSceneLoad) -> SrResult<SceneLoadStatus<K>>
+    where
+        K: From<ResourceKey>,
+    {
+        if let Some((scene, loaded, skipped_primitives)) = pending.advance(&self.core, SCENE_UPLOAD_BUDGET)? {
+            return Ok(SceneLoadStatus::Ready(self.add_loaded_scene(
+                &scene,
+                loaded,
+                skipped_primitives,
+            )?));
+        }
+
+        Ok(match pending.progress() {
+            Some((uploaded, total)) => SceneLoadStatus::Uploading { uploaded, total },
+            None => SceneLoadStatus::Decoding,
+        })
+    }
+
+    /// Register the uploaded assets of `scene` under a new asset group, and
+    /// hand the instances back: the half of [`Self::load_scene`] shared with
+    /// [`Self::poll_scene_load`].
+    fn add_loaded_scene(
+        &mut self,
+        scene: &Scene,
+        loaded: LoadedScene,
+        skipped_primitives: Vec<vulkan_abstraction::gltf::SkippedPrimitive>,
+    ) -> SrResult<SceneLoad<K>>
+    where
+        K: From<ResourceKey>,
+    {
         let group = self.next_group;
         self.next_group += 1;
 
+        let skins = scene.skins().to_vec();
+
         let LoadedScene {
             blases,
             instances,
+            instance_nodes,
+            deformed_blases,
             textures,
             sampler_descs,
             images,
-        } = scene.load_into_gpu(&self.core, scene_data)?;
+            cameras,
+            lights,
+            buffer_uploads,
+            texture_uploads,
+        } = loaded;
 
         let mut group_keys: Vec<K> = Vec::new();
         let mut next_index = 0u64;
@@ -822,25 +1139,140 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             key
         };
 
-        let blas_keys = self
-            .resource_manager
-            .add_scene_assets(blases, textures, sampler_descs, images, &mut make_key)?;
+        // the resource manager takes the deformed BLASes: keep what the caller gets back about them
+        let deformed_placements: Vec<_> = deformed_blases
+            .iter()
+            .map(|deformed| {
+                let morph_target_count = deformed.morphing.as_ref().map_or(0, |morphing| morphing.target_count());
+                (deformed.transform, deformed.node, deformed.skin, morph_target_count)
+            })
+            .collect();
+
+        // No GPU wait: the frames in flight only read what is already registered. The new
+        // assets take fresh arena and heap slots whose copies ride the next frame's prologue,
+        // and the emissive indirection and instance buffers are rebuilt by every frame.
+        let (blas_keys, deformed_keys) =
+            self.resource_manager
+                .add_scene_assets(blases, deformed_blases, textures, sampler_descs, images, &mut make_key)?;
+        // the images and geometry are filled by the same prologue, ahead of the BLAS builds
+        self.resource_manager.queue_uploads(buffer_uploads, texture_uploads);
+        for light in lights {
+            self.resource_manager.add_light(make_key(), light);
+        }
         drop(make_key);
         self.scene_groups.insert(group, group_keys);
 
-        // Group the per-instance transforms by BLAS key, preserving order.
-        let mut grouped: Vec<(K, Vec<vk::TransformMatrixKHR>)> = blas_keys.iter().map(|&k| (k, Vec::new())).collect();
-        for (blas_index, transform) in instances {
-            grouped[blas_index].1.push(transform);
+        let (mut grouped, mut grouped_nodes) = group_instances(&blas_keys, instances, instance_nodes);
+
+        let mut deformed_instances = Vec::with_capacity(deformed_keys.len());
+        for (key, (transform, node, skin, morph_target_count)) in deformed_keys.into_iter().zip(deformed_placements) {
+            grouped.push((key, vec![transform]));
+            grouped_nodes.push(vec![node]);
+            deformed_instances.push(DeformedInstance {
+                key,
+                node,
+                skin,
+                morph_target_count,
+            });
         }
 
-        self.clear_image_dependent_data();
-        if let Some(sc) = &self.swapchain_data {
-            let images = sc.swapchain.images().to_vec();
-            self.build_image_dependent_data(&images)?;
-        }
+        Ok(SceneLoad {
+            group,
+            instances: grouped,
+            instance_nodes: grouped_nodes,
+            graph: scene.graph(),
+            cameras,
+            skins,
+            deformed_instances,
+            skipped_primitives,
+        })
+    }
+
+    /// Pose the skinned instance whose BLAS is registered under `key` (see
+    /// [`SceneLoad::deformed_instances`]): one matrix per joint of its skin, as
+    /// computed by [`vulkan_abstraction::gltf::Skin::joint_matrices`]. The next
+    /// frame skins the mesh and refits its BLAS; poses set in between frames
+    /// replace each other.
+    ///
+    /// Only the geometry follows the pose: the mesh's emissive triangles, used
+    /// to sample it as a light, stay in the rest pose.
+    pub fn set_joint_matrices(&mut self, key: &K, joint_matrices: &[nalgebra::Matrix4<f32>]) -> SrResult<()> {
+        self.resource_manager.set_joint_matrices(key, joint_matrices)
+    }
+
+    /// Set the morph target weights of the instance whose BLAS is registered
+    /// under `key` (see [`SceneLoad::deformed_instances`]): one per target. The
+    /// next frame blends the targets, skins the result if the instance has a
+    /// skin, and refits its BLAS; weights set in between frames replace each
+    /// other. As with poses, the emissive triangles stay undeformed.
+    pub fn set_morph_weights(&mut self, key: &K, weights: &[f32]) -> SrResult<()> {
+        self.resource_manager.set_morph_weights(key, weights)
+    }
+
+    /// The material of the mesh whose BLAS is registered under `key`, as
+    /// loaded or last set.
+    pub fn material(&self, key: &K) -> Option<&vulkan_abstraction::gltf::Material> {
+        self.resource_manager.material(key)
+    }
+
+    /// Replace the material of the mesh whose BLAS is registered under `key`,
+    /// from the next frame on, without reloading it. Texture indices refer to
+    /// the textures of the scene the mesh was loaded with (a mesh added with
+    /// [`Self::load_mesh`] has none).
+    ///
+    /// A mesh loaded opaque can't become masked or blended, and a mesh that
+    /// wasn't emissive when loaded glows but isn't sampled as a light.
+    pub fn set_material(&mut self, key: &K, material: &vulkan_abstraction::gltf::Material) -> SrResult<()> {
+        self.resource_manager.set_material(key, material)
+    }
+
+    /// Change some fields of the material of the mesh registered under `key`
+    /// (see [`Self::set_material`]).
+    pub fn edit_material(&mut self, key: &K, edit: impl FnOnce(&mut vulkan_abstraction::gltf::Material)) -> SrResult<()> {
+        let mut material = self
+            .material(key)
+            .ok_or_else(|| SrError::new_custom("edit_material: no mesh is registered under this key".to_string()))?
+            .clone();
+        edit(&mut material);
+        self.set_material(key, &material)
+    }
+
+    pub fn set_base_color_factor(&mut self, key: &K, base_color_factor: [f32; 4]) -> SrResult<()> {
+        self.edit_material(key, |material| {
+            material.pbr_metallic_roughness_properties.base_color_factor = base_color_factor
+        })
+    }
+
+    pub fn set_metallic_factor(&mut self, key: &K, metallic_factor: f32) -> SrResult<()> {
+        self.edit_material(key, |material| {
+            material.pbr_metallic_roughness_properties.metallic_factor = metallic_factor
+        })
+    }
 
-        Ok((group, grouped))
+    pub fn set_roughness_factor(&mut self, key: &K, roughness_factor: f32) -> SrResult<()> {
+        self.edit_material(key, |material| {
+            material.pbr_metallic_roughness_properties.roughness_factor = roughness_factor
+        })
+    }
+
+    pub fn set_emissive(&mut self, key: &K, emissive_factor: [f32; 3], emissive_strength: f32) -> SrResult<()> {
+        self.edit_material(key, |material| {
+            material.emissive_factor = emissive_factor;
+            material.emissive_strength = emissive_strength;
+        })
+    }
+
+    /// Cap the GPU memory the scene textures take, [`DEFAULT_TEXTURE_BUDGET`]
+    /// by default. Their larger mip levels are uploaded as the frames sample
+    /// them, and the ones sampled least recently are evicted (down to a small
+    /// resident tail, see [`RESIDENT_TAIL_EXTENT`]) when they don't fit.
+    pub fn set_texture_budget(&mut self, bytes: u64) {
+        self.resource_manager.set_texture_budget(bytes);
+    }
+
+    /// How many bytes of scene texture mip levels are on the GPU.
+    pub fn resident_texture_bytes(&self) -> u64 {
+        self.resource_manager.resident_texture_bytes()
     }
 
     /// Free every asset created by the `load_scene` call that returned `group`.
@@ -856,6 +1288,51 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         Ok(())
     }
 
+    /// Replace what rays leaving the scene see, which also lights the scene
+    /// (see [`Environment`]). Waits for the GPU to go idle, since in-flight
+    /// frames may still read the previous environment.
+    pub fn set_environment(&mut self, environment: &Environment) -> SrResult<()> {
+        let resources = environment::EnvironmentResources::new(Rc::clone(&self.core), environment)?;
+        unsafe { self.core.device().inner().device_wait_idle() }?;
+        self.environment = resources;
+        Ok(())
+    }
+
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Switch what the next frames draw. Coming back to the path tracer
+    /// restarts its accumulation, whose history is out of date by then.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        if render_mode != self.render_mode {
+            self.render_mode = render_mode;
+            self.relative_frame_count = 0;
+        }
+    }
+
+    /// Use a procedural [`Sky`] as the environment, its sun lighting the scene
+    /// as a directional light. When the environment already is a sky this only
+    /// updates its parameters (for the next frame) and doesn't wait for the
+    /// GPU, so it's cheap enough to animate the time of day.
+    pub fn set_sky(&mut self, sky: &Sky) -> SrResult<()> {
+        if self.environment.update_sky(sky) {
+            return Ok(());
+        }
+        self.set_environment(&Environment::Sky(*sky))
+    }
+
+    /// Load an equirectangular HDR image (.hdr, .exr, ...) and use it as the
+    /// environment, scaled by `intensity`. Shorthand for
+    /// [`EnvironmentMap::from_file`] + [`Self::set_environment`].
+    pub fn load_environment_map(&mut self, path: &str, intensity: f32) -> SrResult<()> {
+        let map = EnvironmentMap {
+            intensity,
+            ..EnvironmentMap::from_file(path)?
+        };
+        self.set_environment(&Environment::Map(map))
+    }
+
     /// Build a BLAS from raw triangle-list mesh data at **runtime** and
     /// register it under the caller-supplied `key` — the runtime equivalent of
     /// one scene BLAS, with no glTF involved (the Bevy integration uses this to
@@ -864,6 +1341,12 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
     /// so all texture references are treated as absent; the scalar/color
     /// factors still apply.
     ///
+    /// Vertices whose normal is left zeroed get a smooth one generated (the
+    /// caller's vertex sharing says where the surface is meant to be smooth),
+    /// and, when the material has a normal texture to orient, the ones whose
+    /// tangent is left zeroed get MikkTSpace-style tangents from the normal
+    /// texture coordinates.
+    ///
     /// Emissive triangles for NEE are derived from the index list when the
     /// material's emission (`emissive_factor * emissive_strength`) is non-zero.
     /// The mesh is renderable by instances passed to [`Self::render`] from the
@@ -898,6 +1381,24 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             )));
         }
 
+        let missing_normals = vertices.iter().any(vulkan_abstraction::gltf::is_normal_missing);
+        let missing_tangents = vertices.iter().any(vulkan_abstraction::gltf::is_tangent_missing);
+        // like the glTF loader, only the normal textures need tangents
+        let generated_tangents =
+            missing_tangents && (material.normal_texture_index.is_some() || material.clearcoat_normal_texture_index.is_some());
+        let vertices: std::borrow::Cow<[vulkan_abstraction::gltf::Vertex]> = if missing_normals || generated_tangents {
+            let mut vertices = vertices.to_vec();
+            if missing_normals {
+                vulkan_abstraction::gltf::generate_smooth_normals(&mut vertices, indices);
+            }
+            if generated_tangents {
+                vulkan_abstraction::gltf::generate_tangents(&mut vertices, indices);
+            }
+            vertices.into()
+        } else {
+            vertices.into()
+        };
+
         let emission = [
             material.emissive_factor[0] * material.emissive_strength,
             material.emissive_factor[1] * material.emissive_strength,
@@ -924,7 +1425,7 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             Vec::new()
         };
 
-        let vertex_buffer = vulkan_abstraction::VertexBuffer::new_for_blas_from_data(Rc::clone(&self.core), vertices)?;
+        let vertex_buffer = vulkan_abstraction::VertexBuffer::new_for_blas_from_data(Rc::clone(&self.core), &vertices[..])?;
         let index_buffer = vulkan_abstraction::IndexBuffer::new_for_blas_from_data(Rc::clone(&self.core), indices)?;
         // Deferred build: the BLAS resource (and its device address) exists now, so
         // instances can reference it immediately, but the actual
@@ -935,19 +1436,13 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             vertex_buffer,
             index_buffer,
             vulkan_abstraction::BuildType::Static,
+            material.is_opaque(),
         )?;
 
         // No image set accompanies a runtime mesh: every texture reference
         // resolves to "absent" (NULL slots ignored by the shader).
-        let resolve = |_: Option<usize>| {
-            (
-                vulkan_abstraction::Material::NULL_TEXTURE_INDEX,
-                vulkan_abstraction::Material::NULL_TEXTURE_INDEX,
-            )
-        };
-        let gpu_material = vulkan_abstraction::Material::new(material, &resolve);
-
-        self.resource_manager.add_blas(key, blas, gpu_material, &emissive_triangles)?;
+        self.resource_manager
+            .add_blas(key, blas, material.clone(), Rc::from([]), emissive_triangles)?;
         // Stash the build job for the next frame's graph to record.
         self.resource_manager.queue_blas_build_job(key, build_job);
         Ok(())
@@ -1010,6 +1505,9 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         self.run_due_end_of_frame_callbacks();
 
         self.resource_manager.start_of_frame(upcoming_frame)?;
+        // after the slot-reuse wait: the feedback of the frame that last used
+        // this slot is complete
+        let texture_feedback_slot = self.resource_manager.stream_textures(upcoming_frame)?;
 
         // ── Per-frame GPU data: CpuToGpu buffers created on the spot, local to
         // this frame. They're moved into an end-of-frame callback at the end of
@@ -1046,6 +1544,8 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             prev_view_proj: prev_view_proj.transpose(),
         };
         let matrices_address = self.matrices_pool[matrices_slot].get_device_address();
+        // Same slot scheme for the environment parameters, which a sky may change every frame.
+        let environment_address = self.environment.upload(matrices_slot)?;
 
         let frame_data = self.resource_manager.frame_instance_data(instances)?;
         let instance_count = frame_data.as_instances.len() as u32;
@@ -1072,6 +1572,10 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
                 matrix: [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0],
             });
         }
+        let emissive_light_count = frame_data.emissive_entries.len() as u32;
+        let mut punctual_lights = frame_data.punctual_lights;
+        punctual_lights.extend(self.environment.sun_light());
+        let punctual_light_count = punctual_lights.len() as u32;
         let mut emissive_entries = frame_data.emissive_entries;
         if emissive_entries.is_empty() {
             emissive_entries.push(vulkan_abstraction::gltf::EmissiveIndirectionEntry {
@@ -1079,6 +1583,9 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
                 entity_id: 0,
             });
         }
+        if punctual_lights.is_empty() {
+            punctual_lights.push(vulkan_abstraction::gltf::PunctualLight::default());
+        }
 
         let instances_buffer = vulkan_abstraction::StagingBuffer::new_from_data(
             Rc::clone(&self.core),
@@ -1094,13 +1601,20 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
             "per-frame instance transforms",
         )?;
-        // Exactly sized: the shader reads num_lights via `GetDimensions`.
+        // Padded like the others: the shader takes the light counts from the
+        // push constant, not from `GetDimensions`.
         let emissive_indirection_buffer = vulkan_abstraction::StagingBuffer::new_from_data(
             Rc::clone(&self.core),
             &emissive_entries,
             vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
             "per-frame emissive indirection",
         )?;
+        let punctual_lights_buffer = vulkan_abstraction::StagingBuffer::new_from_data(
+            Rc::clone(&self.core),
+            &punctual_lights,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            "per-frame punctual lights",
+        )?;
 
         // The TLAS build (and any pending BLAS builds) are no longer submitted
         // synchronously here — they're recorded into the unified graph below by
@@ -1111,8 +1625,13 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
         let frame_gpu_data = FrameGpuData {
             matrices_address,
+            environment_address,
             entity_transforms_slot: transforms_buffer.raw().storage_slot(),
             emissive_indirection_slot: emissive_indirection_buffer.raw().storage_slot(),
+            punctual_lights_slot: punctual_lights_buffer.raw().storage_slot(),
+            emissive_light_count,
+            punctual_light_count,
+            texture_feedback_slot,
         };
 
         if !self.image_dependant_data.contains_key(&dst_image) {
@@ -1200,6 +1719,7 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
                 drop(instances_buffer);
                 drop(transforms_buffer);
                 drop(emissive_indirection_buffer);
+                drop(punctual_lights_buffer);
             }),
         ));
 
@@ -1364,7 +1884,8 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
 
         // Non-image fields of the RT push constant: stable slots come from the
         // resource manager, the per-frame ones (matrices address, transforms /
-        // emissive indirection slots) from this frame's local buffers. The five
+        // emissive indirection / punctual light slots and the light counts)
+        // from this frame's local buffers. The five
         // RT-output image slots are filled inside the closure from the graph's
         // transient resources (they're created per frame).
         // `tlas` is filled below with the address returned by `queue_tlas_build`
@@ -1389,6 +1910,13 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             } else {
                 0
             },
+            punctual_lights: pack(frame_gpu_data.punctual_lights_slot),
+            emissive_light_count: frame_gpu_data.emissive_light_count,
+            punctual_light_count: frame_gpu_data.punctual_light_count,
+            environment: frame_gpu_data.environment_address,
+            environment_cdf: pack(self.environment.cdf_slot()),
+            texture_feedback: pack(frame_gpu_data.texture_feedback_slot),
+            image_slots: pack(self.resource_manager.image_slots_storage_slot()),
             ..Default::default()
         };
 
@@ -1414,6 +1942,8 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         };
         let ris_shaders = make_rt_shaders(self.ray_gen_ris_spirv);
         let final_shaders = make_rt_shaders(self.ray_gen_final_spirv);
+        let ao_shaders = make_rt_shaders(self.ray_gen_ao_spirv);
+        let render_mode = self.render_mode;
 
         // Compute passes now describe themselves with their SPIR-V; the graph's
         // pipeline cache builds/reuses the pipeline. Snapshot the bytes into
@@ -1421,6 +1951,8 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         let taa_spirv = self.temporal_accumulation_spirv;
         let denoise_spirv = self.denoise_spirv;
         let postprocess_spirv = self.postprocess_spirv;
+        let skinning_spirv = self.skinning_spirv;
+        let morph_spirv = self.morph_spirv;
 
         let postprocess_out_arc = Arc::clone(postprocess_out);
 
@@ -1443,17 +1975,19 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         // (ordered before the shader reads). Drained before the `rg` borrow so it
         // stays disjoint from `self.resource_manager`.
         let arena_copies = self.resource_manager.take_queued_copies();
+        let texture_copies = self.resource_manager.take_queued_texture_copies();
 
         let rg = &mut self.render_graph;
         rg.reset();
         rg.add_prologue_buffer_copies(arena_copies);
+        rg.add_prologue_texture_copies(texture_copies);
 
         // Record this frame's acceleration-structure builds into the graph before
         // any consumer: pending BLAS builds first (so the TLAS build orders itself
         // after them via graph read edges), then the TLAS build/update. The RT
         // passes below declare a read on `tlas_h`, so `compile` emits the
         // build→trace barrier itself — no separate synchronous AS submit.
-        let built_blases = self.resource_manager.queue_blas_builds(rg)?;
+        let built_blases = self.resource_manager.queue_blas_builds(rg, skinning_spirv, morph_spirv)?;
         let blas_deps: Vec<_> = built_blases.iter().map(|(_, handle)| handle.clone()).collect();
         let (tlas_h, tlas_address) = self
             .resource_manager
@@ -1479,6 +2013,15 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             Box::new(|renderer: &mut Renderer<K>| renderer.resource_manager.mark_tlas_built()),
         ));
 
+        // The ambient occlusion view is one trace straight into the output.
+        if let RenderMode::AmbientOcclusion { radius } = render_mode {
+            rt_pc_base.ao_radius = radius;
+            let postprocess_out_h = rg.import::<ImageDesc>(postprocess_out_arc);
+            Self::add_ambient_occlusion_pass(rg, ao_shaders, rt_pc_base, postprocess_out_h, tlas_h, extent)?;
+            rg.compile()?;
+            return Ok(());
+        }
+
         let mk_img = |format: vk::Format, usage: vk::ImageUsageFlags, name: &'static str| ImageDesc {
             extent,
             format,
@@ -1639,16 +2182,20 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         pc.normal_img = pack(tr.image(normal_h)?.storage_slot());
         pc.diffuse_img = pack(tr.image(diffuse_h)?.storage_slot());
         pc.motion_vec_img = pack(tr.image(motion_h)?.storage_slot());
+        Ok(Self::push_constant_bytes(&pc))
+    }
+
+    /// The raw bytes of `pc` the ray-tracing passes push via `cmd_push_data`.
+    fn push_constant_bytes(pc: &vulkan_abstraction::RaytracingHeapPushConstant) -> Vec<u8> {
         // `RaytracingHeapPushConstant` is `#[repr(C)]` plain data, so a verbatim
         // byte copy matches the shader's push-constant layout.
-        let bytes = unsafe {
+        unsafe {
             std::slice::from_raw_parts(
-                &pc as *const vulkan_abstraction::RaytracingHeapPushConstant as *const u8,
+                pc as *const vulkan_abstraction::RaytracingHeapPushConstant as *const u8,
                 size_of::<vulkan_abstraction::RaytracingHeapPushConstant>(),
             )
         }
-        .to_vec();
-        Ok(bytes)
+        .to_vec()
     }
 
     /// RIS audition ray-tracing pass, built through the standard
@@ -1754,6 +2301,37 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
         Ok(())
     }
 
+    /// Ambient occlusion ray-tracing pass ([`RenderMode::AmbientOcclusion`]),
+    /// built like the path tracer's passes. `ray_gen_ao` reaches `output_h`
+    /// through the push constant's `raw_color` slot and touches no other
+    /// image.
+    fn add_ambient_occlusion_pass(
+        rg: &mut RenderGraph,
+        shaders: RayTracingShaders,
+        pc_base: vulkan_abstraction::RaytracingHeapPushConstant,
+        output_h: Handle<vulkan_abstraction::Image>,
+        tlas_h: Handle<vulkan_abstraction::AccelerationStructure>,
+        extent: vk::Extent3D,
+    ) -> SrResult<()> {
+        let mut common = PassCommonDataBuilder::new(rg, "raytracing_ambient_occlusion");
+        common.write(&output_h, vk_sync::AccessType::General)?;
+        common.read(&tlas_h, vk_sync::AccessType::RayTracingShaderReadAccelerationStructure)?;
+
+        let pass = RaytracingRenderPassBuilder::default()
+            .common(common.build())
+            .shaders(shaders)
+            .trace_extent([extent.width, extent.height, extent.depth])
+            .generate_render(rg, move |tr| {
+                let mut pc = pc_base;
+                pc.raw_color = [tr.image(&output_h)?.storage_slot(), 0];
+                Ok(Self::push_constant_bytes(&pc))
+            })?
+            .build()
+            .map_err(|e| SrError::new_custom(format!("ambient occlusion pass builder failed: {e}")))?;
+        rg.add_render_pass(pass);
+        Ok(())
+    }
+
     /// Temporal accumulation graph node (heap + Slang). Reads the RT raw color +
     /// motion vectors and the history accumulation image, writes the target
     /// accumulation image.
@@ -1969,12 +2547,13 @@ impl<K: Hash + Eq + Copy + 'static> Renderer<K> {
             .dst_offsets(dst_offsets);
 
         unsafe {
-            //transition src_image from general to transfer source layout
+            //transition src_image from general to transfer source layout; written by the
+            //postprocess pass, or by the ray tracing of the ambient occlusion render mode
             vulkan_abstraction::cmd_image_memory_barrier(
                 core,
                 cmd_buf,
                 src_image,
-                vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::PipelineStageFlags2::COMPUTE_SHADER | vk::PipelineStageFlags2::RAY_TRACING_SHADER_KHR,
                 vk::PipelineStageFlags2::TRANSFER,
                 vk::AccessFlags2::SHADER_WRITE,
                 vk::AccessFlags2::TRANSFER_READ,
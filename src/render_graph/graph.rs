@@ -12,7 +12,7 @@ use crate::vulkan_abstraction::image::ImageDesc;
 use crate::vulkan_abstraction::{
     AccelerationStructure, AsBuildJob, CmdBuffer, ComputePipeline, Core, GpuOnlyBuffer, GraphicsPipeline,
     GraphicsPipelineShaders, HeapComputePass, Image, Pipeline, RawBuffer, RayTracingPipeline, RayTracingPipelineShaders,
-    ShaderBindingTable, TimelineSemaphore,
+    ShaderBindingTable, TextureCopy, TimelineSemaphore,
 };
 use ash::vk;
 use petgraph::visit::EdgeRef;
@@ -314,6 +314,10 @@ pub struct RenderGraph {
     /// this frame's submission (handed over by the resource manager on asset
     /// load). Cleared on `reset`. See [`Self::add_prologue_buffer_copies`].
     prologue_copies: Vec<(vk::Buffer, vk::Buffer, vk::BufferCopy)>,
+    /// Staging→image copies filling the textures streamed in this frame, recorded
+    /// in the same prologue. Cleared on `reset`. See
+    /// [`Self::add_prologue_texture_copies`].
+    prologue_texture_copies: Vec<TextureCopy>,
     /// Signaled with the absolute frame count when each frame's graph submission
     /// completes. Drives CPU slot-reuse gating, the cross-frame temporal
     /// ping-pong wait (frame F's graph waits F-1's), and the blit's wait on the
@@ -355,6 +359,7 @@ impl RenderGraph {
             retired_passes,
             retired_resources,
             prologue_copies: vec![],
+            prologue_texture_copies: vec![],
             graph_timeline,
             core,
         })
@@ -391,7 +396,8 @@ impl RenderGraph {
 
     /// Hand the graph a batch of arena staging→GPU buffer copies to record as a
     /// transfer prologue at the head of this frame's submission (before any pass),
-    /// followed by a transfer→shader-read barrier. The arena buffers are
+    /// followed by a transfer→shader-read barrier (which the BLAS builds of
+    /// freshly uploaded geometry buffers wait on too). The arena buffers are
     /// program-lifetime with CPU-side frame ring buffering and are only reached by
     /// device address in shaders, so they are *not* tracked as graph resources —
     /// this only guarantees the copy is ordered before the reads.
@@ -399,6 +405,15 @@ impl RenderGraph {
         self.prologue_copies.append(&mut copies);
     }
 
+    /// Hand the graph texture uploads to record in the same transfer prologue,
+    /// each followed by the transition of its image to shader-read. The images
+    /// and staging buffers are owned by the caller, which keeps them alive
+    /// until this frame's submission is complete; like the arenas they're
+    /// reached through the descriptor heap, so they are *not* graph resources.
+    pub fn add_prologue_texture_copies(&mut self, mut copies: Vec<TextureCopy>) {
+        self.prologue_texture_copies.append(&mut copies);
+    }
+
     /// Clear all per-frame state (passes, virtual resources, transient bindings,
     /// swapchain import, recorded barrier trace) so the graph can be rebuilt
     /// from scratch on the next frame. The persistent `CmdBuffer` and cached
@@ -412,6 +427,7 @@ impl RenderGraph {
         self.passes.clear();
         self.virtual_resources.clear();
         self.prologue_copies.clear();
+        self.prologue_texture_copies.clear();
         self.swapchain_resource_id = None;
         self.resource_end_states.clear();
         self.registered_temporal.clear();
@@ -665,6 +681,39 @@ impl RenderGraph {
         build_target: &Handle<AccelerationStructure>,
         deps: &[Handle<AccelerationStructure>],
         job: AsBuildJob,
+    ) -> SrResult<()> {
+        self.add_as_build_pass_with_target_access(
+            name,
+            build_target,
+            vk_sync::AccessType::AccelerationStructureBuildWrite,
+            deps,
+            job,
+        )
+    }
+
+    /// Like [`Self::add_as_build_pass`], for a BLAS whose geometry an earlier
+    /// pass of this graph wrote (e.g. the skinning pass), declaring that write on
+    /// `build_target` since geometry buffers aren't graph resources. A build reads
+    /// its geometry as `SHADER_READ` in the build stage, which an
+    /// `AccelerationStructureBuildWrite` barrier doesn't make the earlier writes
+    /// visible to: the target is declared `General` instead, so the barrier
+    /// covers them.
+    pub fn add_as_refit_pass(
+        &mut self,
+        name: &str,
+        build_target: &Handle<AccelerationStructure>,
+        job: AsBuildJob,
+    ) -> SrResult<()> {
+        self.add_as_build_pass_with_target_access(name, build_target, vk_sync::AccessType::General, &[], job)
+    }
+
+    fn add_as_build_pass_with_target_access(
+        &mut self,
+        name: &str,
+        build_target: &Handle<AccelerationStructure>,
+        target_access: vk_sync::AccessType,
+        deps: &[Handle<AccelerationStructure>],
+        job: AsBuildJob,
     ) -> SrResult<()> {
         let scratch = GpuOnlyBuffer::new_aligned::<u8>(
             Rc::clone(&self.core),
@@ -675,7 +724,7 @@ impl RenderGraph {
         )?;
 
         let mut common = PassCommonDataBuilder::new(self, name);
-        common.write(build_target, vk_sync::AccessType::AccelerationStructureBuildWrite)?;
+        common.write(build_target, target_access)?;
         for dep in deps {
             common.read(dep, vk_sync::AccessType::AccelerationStructureBuildRead)?;
         }
@@ -973,9 +1022,10 @@ impl RenderGraph {
         // Transfer prologue: arena staging→GPU copies (queued by the resource
         // manager on asset load) recorded at the head of this submission, then one
         // buffer barrier so the RT/compute passes that read the arenas (by device
-        // address) see the writes. Recorded outside the hazard graph on purpose —
-        // the arenas are program-lifetime, CPU ring-buffered, and never graph
-        // resources (see `add_prologue_buffer_copies`).
+        // address) see the writes, as do the BLAS builds of freshly uploaded
+        // geometry. Recorded outside the hazard graph on purpose — the arenas are
+        // program-lifetime, CPU ring-buffered, and never graph resources (see
+        // `add_prologue_buffer_copies`).
         if !self.prologue_copies.is_empty() {
             let unique_dsts: HashSet<vk::Buffer> = self.prologue_copies.iter().map(|(_, dst, _)| *dst).collect();
             let barriers: Vec<vk::BufferMemoryBarrier2> = unique_dsts
@@ -984,7 +1034,11 @@ impl RenderGraph {
                     vk::BufferMemoryBarrier2::default()
                         .src_stage_mask(vk::PipelineStageFlags2::TRANSFER)
                         .src_access_mask(vk::AccessFlags2::TRANSFER_WRITE)
-                        .dst_stage_mask(vk::PipelineStageFlags2::RAY_TRACING_SHADER_KHR | vk::PipelineStageFlags2::COMPUTE_SHADER)
+                        .dst_stage_mask(
+                            vk::PipelineStageFlags2::RAY_TRACING_SHADER_KHR
+                                | vk::PipelineStageFlags2::COMPUTE_SHADER
+                                | vk::PipelineStageFlags2::ACCELERATION_STRUCTURE_BUILD_KHR,
+                        )
                         .dst_access_mask(vk::AccessFlags2::SHADER_READ)
                         .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
                         .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
@@ -1002,6 +1056,72 @@ impl RenderGraph {
             }
         }
 
+        // Texture uploads of the streamed mip levels, in the same prologue: the
+        // images are fresh, so they go from UNDEFINED to transfer-dst, get their
+        // levels and move to shader-read before any pass can sample them.
+        if !self.prologue_texture_copies.is_empty() {
+            let barrier = |copy: &TextureCopy,
+                           src: (vk::PipelineStageFlags2, vk::AccessFlags2, vk::ImageLayout),
+                           dst: (vk::PipelineStageFlags2, vk::AccessFlags2, vk::ImageLayout)| {
+                vk::ImageMemoryBarrier2::default()
+                    .src_stage_mask(src.0)
+                    .src_access_mask(src.1)
+                    .old_layout(src.2)
+                    .dst_stage_mask(dst.0)
+                    .dst_access_mask(dst.1)
+                    .new_layout(dst.2)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(copy.image)
+                    .subresource_range(
+                        vk::ImageSubresourceRange::default()
+                            .aspect_mask(vk::ImageAspectFlags::COLOR)
+                            .base_mip_level(0)
+                            .level_count(copy.mip_levels)
+                            .base_array_layer(0)
+                            .layer_count(1),
+                    )
+            };
+            let undefined = (
+                vk::PipelineStageFlags2::NONE,
+                vk::AccessFlags2::empty(),
+                vk::ImageLayout::UNDEFINED,
+            );
+            let transfer_dst = (
+                vk::PipelineStageFlags2::TRANSFER,
+                vk::AccessFlags2::TRANSFER_WRITE,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            );
+            let shader_read = (
+                vk::PipelineStageFlags2::RAY_TRACING_SHADER_KHR | vk::PipelineStageFlags2::COMPUTE_SHADER,
+                vk::AccessFlags2::SHADER_SAMPLED_READ,
+                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            );
+            let to_transfer_dst: Vec<_> = self
+                .prologue_texture_copies
+                .iter()
+                .map(|copy| barrier(copy, undefined, transfer_dst))
+                .collect();
+            let to_shader_read: Vec<_> = self
+                .prologue_texture_copies
+                .iter()
+                .map(|copy| barrier(copy, transfer_dst, shader_read))
+                .collect();
+            unsafe {
+                device.cmd_pipeline_barrier2(raw_cb, &vk::DependencyInfo::default().image_memory_barriers(&to_transfer_dst));
+                for copy in &self.prologue_texture_copies {
+                    device.cmd_copy_buffer_to_image(
+                        raw_cb,
+                        copy.staging_buffer,
+                        copy.image,
+                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                        &copy.regions,
+                    );
+                }
+                device.cmd_pipeline_barrier2(raw_cb, &vk::DependencyInfo::default().image_memory_barriers(&to_shader_read));
+            }
+        }
+
         // Initial layout transitions for created (transient) images. Their memory
         // is freshly bound this frame, so the image is in UNDEFINED; the first
         // pass that touches one accesses it through a storage/sampled descriptor
@@ -1416,6 +1536,7 @@ mod tests {
                 address_mode_v: vk::SamplerAddressMode::REPEAT,
                 address_mode_w: vk::SamplerAddressMode::REPEAT,
                 mipmap_mode: vk::SamplerMipmapMode::LINEAR,
+                mipmapped: false,
             })),
         ];
 
@@ -1,17 +1,99 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
 
 use crate::vulkan_abstraction::image::sampler::SamplerDesc;
-use crate::{error::SrResult, vulkan_abstraction};
+use crate::{
+    error::{SrError, SrResult},
+    vulkan_abstraction,
+};
 
 use crate::utils::na_mat4_to_vk_transform;
 use ash::vk;
+use nalgebra as na;
 
 pub struct SceneData {
     pub textures: Vec<vulkan_abstraction::gltf::Texture>,
     pub samplers: Vec<vulkan_abstraction::gltf::Sampler>,
     pub images: Vec<vulkan_abstraction::gltf::Image>,
     pub primitive_data_map: vulkan_abstraction::gltf::PrimitiveDataMap,
+    /// Primitives the glTF loader could not turn into triangles.
+    pub skipped_primitives: Vec<vulkan_abstraction::gltf::SkippedPrimitive>,
 }
+
+impl SceneData {
+    /// Turn the images into the mip levels they are uploaded as, in a format
+    /// the device samples: decoded images are converted and get their mip
+    /// chain built, UASTC ones are transcoded. This is the CPU half of the
+    /// image uploads, which a background scene load runs on its worker thread.
+    pub fn prepare_images(&mut self, formats: &TextureFormatSupport) -> SrResult<()> {
+        let full_resolution_images = full_resolution_images(&self.textures, &sampler_descs(&self.samplers));
+        self.images = std::mem::take(&mut self.images)
+            .into_iter()
+            .enumerate()
+            .map(|(index, image)| prepare_image(image, full_resolution_images.contains(&index), formats))
+            .collect::<SrResult<_>>()?;
+        Ok(())
+    }
+}
+
+/// The format support [`SceneData::prepare_images`] picks upload formats
+/// from, queried ahead of time so that threads without the device can
+/// prepare images too.
+#[derive(Clone, Debug)]
+pub struct TextureFormatSupport {
+    /// The optimal tiling features of every format [`upload_formats`] offers.
+    features: HashMap<vk::Format, vk::FormatFeatureFlags>,
+    bc: bool,
+}
+
+impl TextureFormatSupport {
+    /// What `core`'s device supports.
+    pub fn query(core: &vulkan_abstraction::Core) -> Self {
+        const DECODED_FORMATS: [gltf::image::Format; 10] = [
+            gltf::image::Format::R8,
+            gltf::image::Format::R8G8,
+            gltf::image::Format::R8G8B8,
+            gltf::image::Format::R8G8B8A8,
+            gltf::image::Format::R16,
+            gltf::image::Format::R16G16,
+            gltf::image::Format::R16G16B16,
+            gltf::image::Format::R16G16B16A16,
+            gltf::image::Format::R32G32B32FLOAT,
+            gltf::image::Format::R32G32B32A32FLOAT,
+        ];
+
+        let features = DECODED_FORMATS
+            .into_iter()
+            .flat_map(upload_formats)
+            .flat_map(|format| {
+                [
+                    with_encoding(format, vulkan_abstraction::gltf::TexelEncoding::Linear),
+                    with_encoding(format, vulkan_abstraction::gltf::TexelEncoding::Srgb),
+                ]
+            })
+            .map(|format| {
+                let features = unsafe {
+                    core.instance()
+                        .get_physical_device_format_properties(core.device().physical_device(), format)
+                }
+                .optimal_tiling_features;
+                (format, features)
+            })
+            .collect();
+
+        Self {
+            features,
+            bc: core.device().supports_texture_compression_bc(),
+        }
+    }
+
+    fn features(&self, format: vk::Format) -> vk::FormatFeatureFlags {
+        self.features.get(&format).copied().unwrap_or_default()
+    }
+}
+
 //TODO I need to actually look into this and decide how to handle it once and for all
 /// One unique BLAS of a loaded scene, together with the data the
 /// `ResourceManager` uploads alongside it: the primitive's material and its
@@ -20,6 +102,24 @@ pub struct LoadedBlas {
     pub blas: vulkan_abstraction::Blas,
     pub material: vulkan_abstraction::gltf::Material,
     pub emissive_triangles: Vec<vulkan_abstraction::gltf::EmissiveTriangle>,
+    /// The build of the BLAS, for the render graph to record, when it was
+    /// uploaded with [`BlasBuild::Deferred`].
+    pub build_job: Option<vulkan_abstraction::AsBuildJob>,
+}
+
+/// The BLAS of one deformed instance. Deformed instances don't share BLASes:
+/// each is deformed into its own vertex buffer.
+pub struct LoadedDeformedBlas {
+    pub loaded: LoadedBlas,
+    pub transform: vk::TransformMatrixKHR,
+    /// glTF index of the node instancing the primitive.
+    pub node: usize,
+    /// Index of the skin in `Scene::skins`.
+    pub skin: Option<usize>,
+    /// `None` when the primitive has no joints and weights to skin it with.
+    pub skinning: Option<vulkan_abstraction::SkinnedGeometry>,
+    /// `None` when the primitive has no morph targets.
+    pub morphing: Option<vulkan_abstraction::MorphedGeometry>,
 }
 
 /// Everything `Scene::load_into_gpu` produced, in a renderer-agnostic form:
@@ -31,165 +131,696 @@ pub struct LoadedScene {
     pub blases: Vec<LoadedBlas>,
     /// One entry per scene instance: index into `blases` + world transform.
     pub instances: Vec<(usize, vk::TransformMatrixKHR)>,
+    /// The glTF node of each entry of `instances`.
+    pub instance_nodes: Vec<usize>,
+    /// Instances deformed by a skin or morph targets, one BLAS each.
+    pub deformed_blases: Vec<LoadedDeformedBlas>,
     pub textures: Vec<vulkan_abstraction::gltf::Texture>,
     pub sampler_descs: Vec<SamplerDesc>,
-    pub images: Vec<vulkan_abstraction::Image>,
+    pub images: Vec<vulkan_abstraction::StreamedTexture>,
+    /// Cameras authored in the scene, in node traversal order, already placed
+    /// with their node's world transform.
+    pub cameras: Vec<crate::Camera>,
+    /// `KHR_lights_punctual` lights of the scene, in world space.
+    pub lights: Vec<vulkan_abstraction::gltf::PunctualLight>,
+    /// The copies filling the images and geometry buffers, for the
+    /// `ResourceManager` to record in the next frame's transfer prologue.
+    pub buffer_uploads: Vec<vulkan_abstraction::BufferUpload>,
+    pub texture_uploads: Vec<vulkan_abstraction::TextureUpload>,
+}
+
+/// What a scene instances, as laid out by `Scene::layout` before anything is
+/// uploaded: `load_into_gpu` turns each primitive into one BLAS, in order.
+pub struct SceneLayout<'a> {
+    /// The scene's unique primitives (by `PrimitiveUniqueKey`), in first-use order.
+    pub primitives: Vec<&'a vulkan_abstraction::gltf::Primitive>,
+    /// One entry per scene instance: index into `primitives` + world transform.
+    pub instances: Vec<(usize, vk::TransformMatrixKHR)>,
+    /// The glTF node of each entry of `instances`.
+    pub instance_nodes: Vec<usize>,
+    /// Instances of primitives deformed by a skin or morph targets. They don't
+    /// share BLASes, so they stay out of `primitives` / `instances`.
+    pub deformed_instances: Vec<DeformedPrimitiveInstance<'a>>,
+    pub cameras: Vec<crate::Camera>,
+    pub lights: Vec<vulkan_abstraction::gltf::PunctualLight>,
+}
+
+/// A primitive instanced by a node with a skin, or with morph targets of its
+/// own, as laid out by `Scene::layout`.
+pub struct DeformedPrimitiveInstance<'a> {
+    pub primitive: &'a vulkan_abstraction::gltf::Primitive,
+    /// glTF index of the node instancing the primitive.
+    pub node: usize,
+    /// Index of the skin in `Scene::skins`, `None` for a primitive only
+    /// deformed by its morph targets.
+    pub skin: Option<usize>,
+    pub transform: vk::TransformMatrixKHR,
+    /// The skin's joint matrices in the pose the scene was authored in, empty
+    /// without a skin.
+    pub joint_matrices: Vec<na::Matrix4<f32>>,
+    /// The node's default weights, one per morph target of the primitive.
+    pub morph_weights: Vec<f32>,
 }
 
 pub struct Scene {
     nodes: Vec<vulkan_abstraction::gltf::Node>,
+    skins: Vec<vulkan_abstraction::gltf::Skin>,
+    animations: Vec<vulkan_abstraction::gltf::SceneAnimation>,
 }
 
 impl Scene {
-    pub fn new(nodes: Vec<vulkan_abstraction::gltf::Node>) -> SrResult<Self> {
-        Ok(Self { nodes })
+    pub fn new(
+        nodes: Vec<vulkan_abstraction::gltf::Node>,
+        skins: Vec<vulkan_abstraction::gltf::Skin>,
+        animations: Vec<vulkan_abstraction::gltf::SceneAnimation>,
+    ) -> SrResult<Self> {
+        Ok(Self {
+            nodes,
+            skins,
+            animations,
+        })
     }
 
     pub fn nodes(&self) -> &[vulkan_abstraction::gltf::Node] {
         &self.nodes
     }
 
-    pub fn load_into_gpu(&self, core: &Rc<vulkan_abstraction::Core>, mut scene_data: crate::SceneData) -> SrResult<LoadedScene> {
-        let mut blases = vec![];
-        let mut instances = vec![];
+    /// The skins of the glTF document, indexed like the nodes' `skin()`.
+    pub fn skins(&self) -> &[vulkan_abstraction::gltf::Skin] {
+        &self.skins
+    }
 
-        let mut primitives_blas_index: HashMap<vulkan_abstraction::gltf::PrimitiveUniqueKey, usize> = HashMap::new();
+    /// The animation clips of the glTF document.
+    pub fn animations(&self) -> &[vulkan_abstraction::gltf::SceneAnimation] {
+        &self.animations
+    }
+
+    /// Re-evaluate the node hierarchy with each node's transform relative to
+    /// its parent given by `local_transform`: the world transform of every
+    /// node, by glTF index.
+    pub fn world_transforms(
+        &self,
+        local_transform: impl Fn(&vulkan_abstraction::gltf::Node) -> na::Matrix4<f32>,
+    ) -> HashMap<usize, na::Matrix4<f32>> {
+        let mut world_transforms = HashMap::new();
         for node in self.nodes() {
-            self.explore_node(
-                node,
-                core,
-                &mut blases,
-                &mut instances,
-                &mut primitives_blas_index,
-                &mut scene_data,
-            )?;
+            // the root nodes do not have a parent transform to apply
+            Self::evaluate_world_transforms(node, &na::Matrix4::identity(), &local_transform, &mut world_transforms);
         }
+        world_transforms
+    }
 
-        let sampler_descs = scene_data
-            .samplers
-            .iter()
-            .map(|sampler| {
-                let default = gltf::texture::MinFilter::Linear;
-
-                SamplerDesc {
-                    min_filter: vk::Filter::from_gltf(sampler.min_filter.unwrap_or(default)),
-                    mag_filter: vk::Filter::from_gltf(sampler.mag_filter.unwrap_or(gltf::texture::MagFilter::Linear)),
-                    address_mode_u: vk::SamplerAddressMode::from_gltf(sampler.wrap_s_u),
-                    address_mode_v: vk::SamplerAddressMode::from_gltf(sampler.wrap_t_v),
-                    address_mode_w: vk::SamplerAddressMode::REPEAT,
-                    mipmap_mode: vk::SamplerMipmapMode::from_gltf(sampler.min_filter.unwrap_or(default)),
-                }
-            })
-            .collect();
+    /// The node hierarchy, with names, parent links and editable local
+    /// transforms.
+    pub fn graph(&self) -> crate::SceneGraph {
+        crate::SceneGraph::new(self.nodes())
+    }
 
-        let images: Result<Vec<_>, _> = scene_data.images.into_iter().map(|image| to_vk_image(core, image)).collect();
+    /// The CPU half of `load_into_gpu`: walks the node hierarchy and lays the
+    /// scene out without touching the GPU.
+    pub fn layout(&self) -> SceneLayout<'_> {
+        let mut layout = SceneLayout {
+            primitives: vec![],
+            instances: vec![],
+            instance_nodes: vec![],
+            deformed_instances: vec![],
+            cameras: vec![],
+            lights: vec![],
+        };
+
+        // joints are nodes anywhere in the hierarchy: know every world transform before posing them
+        let mut world_transforms = HashMap::new();
+        for node in self.nodes() {
+            Self::collect_world_transforms(node, &mut world_transforms);
+        }
 
-        Ok(LoadedScene {
-            blases,
-            instances,
-            textures: scene_data.textures,
-            sampler_descs,
-            images: images?,
-        })
+        let mut primitives_blas_index: HashMap<vulkan_abstraction::gltf::PrimitiveUniqueKey, usize> = HashMap::new();
+        for node in self.nodes() {
+            self.explore_node(node, &world_transforms, &mut layout, &mut primitives_blas_index);
+        }
+
+        layout
     }
 
-    fn explore_node(
-        &self,
+    pub fn load_into_gpu(&self, core: &Rc<vulkan_abstraction::Core>, mut scene_data: crate::SceneData) -> SrResult<LoadedScene> {
+        scene_data.prepare_images(&TextureFormatSupport::query(core))?;
+        let mut upload = SceneUpload::new(self, scene_data, BlasBuild::OnUpload);
+        while !upload.upload_some(core, usize::MAX)? {}
+        Ok(upload.finish())
+    }
+
+    fn evaluate_world_transforms(
         node: &vulkan_abstraction::gltf::Node,
-        core: &Rc<vulkan_abstraction::Core>,
-        blases: &mut Vec<LoadedBlas>,
-        instances: &mut Vec<(usize, vk::TransformMatrixKHR)>,
+        parent_transform: &na::Matrix4<f32>,
+        local_transform: &impl Fn(&vulkan_abstraction::gltf::Node) -> na::Matrix4<f32>,
+        world_transforms: &mut HashMap<usize, na::Matrix4<f32>>,
+    ) {
+        let transform = parent_transform * local_transform(node);
+        world_transforms.insert(node.index(), transform);
+
+        if let Some(children) = node.children() {
+            for child in children {
+                Self::evaluate_world_transforms(child, &transform, local_transform, world_transforms);
+            }
+        }
+    }
+
+    fn collect_world_transforms(node: &vulkan_abstraction::gltf::Node, world_transforms: &mut HashMap<usize, na::Matrix4<f32>>) {
+        world_transforms.insert(node.index(), *node.transform());
+
+        if let Some(children) = node.children() {
+            for child in children {
+                Self::collect_world_transforms(child, world_transforms);
+            }
+        }
+    }
+
+    fn explore_node<'a>(
+        &'a self,
+        node: &'a vulkan_abstraction::gltf::Node,
+        world_transforms: &HashMap<usize, na::Matrix4<f32>>,
+        layout: &mut SceneLayout<'a>,
         primitives_blas_index: &mut HashMap<vulkan_abstraction::gltf::PrimitiveUniqueKey, usize>,
-        scene_data: &mut crate::SceneData,
-    ) -> SrResult<()> {
+    ) {
+        let skin = node.skin().and_then(|index| Some((index, self.skins.get(index)?)));
+
         if let Some(mesh) = node.mesh() {
             for primitive in mesh.primitives() {
-                let primitive_unique_key = primitive.unique_key;
-
-                let blas_index = match primitives_blas_index.get(&primitive_unique_key) {
-                    Some(blas_index) => *blas_index,
-                    None => {
-                        let primitive_data = scene_data.primitive_data_map.remove(&primitive_unique_key).unwrap();
-
-                        // Convert local-space emissive triangles for this primitive
-                        let emissive_triangles: Vec<_> = if !primitive.local_emissive_triangles.is_empty() {
-                            let material = &primitive.material;
-                            let emission = [
-                                material.emissive_factor[0] * material.emissive_strength,
-                                material.emissive_factor[1] * material.emissive_strength,
-                                material.emissive_factor[2] * material.emissive_strength,
-                                0.0,
-                            ];
-                            primitive
-                                .local_emissive_triangles
-                                .iter()
-                                .map(|local_tri| vulkan_abstraction::gltf::EmissiveTriangle {
-                                    v0: [local_tri[0].x, local_tri[0].y, local_tri[0].z, 0.0],
-                                    v1: [local_tri[1].x, local_tri[1].y, local_tri[1].z, 0.0],
-                                    v2: [local_tri[2].x, local_tri[2].y, local_tri[2].z, 0.0],
-                                    emission,
-                                })
-                                .collect()
-                        } else {
-                            Vec::new()
-                        };
-
-                        let blas = vulkan_abstraction::Blas::new(
-                            core.clone(),
-                            primitive_data.vertex_buffer,
-                            primitive_data.index_buffer,
-                            vulkan_abstraction::BuildType::Static,
-                        )?;
-
-                        blases.push(LoadedBlas {
-                            blas,
-                            material: primitive.material.clone(),
-                            emissive_triangles,
-                        });
-
-                        let blas_index = blases.len() - 1;
-                        primitives_blas_index.insert(primitive_unique_key, blas_index);
-
-                        blas_index
-                    }
-                };
-
-                instances.push((blas_index, na_mat4_to_vk_transform(*node.transform())));
+                // morph weights are per instance: a primitive with morph targets gets a BLAS of its own too
+                if skin.is_some() || primitive.morph_target_count > 0 {
+                    // a joint that is not part of the scene sits at the origin
+                    let joint_matrices = skin.map_or(vec![], |(_, skin)| {
+                        skin.joint_matrices(node.transform(), |joint| {
+                            world_transforms.get(&joint).copied().unwrap_or_else(na::Matrix4::identity)
+                        })
+                    });
+
+                    let mut morph_weights = node.morph_weights().to_vec();
+                    morph_weights.resize(primitive.morph_target_count, 0.0);
+
+                    layout.deformed_instances.push(DeformedPrimitiveInstance {
+                        primitive,
+                        node: node.index(),
+                        skin: skin.map(|(skin_index, _)| skin_index),
+                        transform: na_mat4_to_vk_transform(*node.transform()),
+                        joint_matrices,
+                        morph_weights,
+                    });
+                    continue;
+                }
+
+                let blas_index = *primitives_blas_index.entry(primitive.unique_key).or_insert_with(|| {
+                    layout.primitives.push(primitive);
+                    layout.primitives.len() - 1
+                });
+
+                layout
+                    .instances
+                    .push((blas_index, na_mat4_to_vk_transform(*node.transform())));
+                layout.instance_nodes.push(node.index());
             }
         }
 
+        if let Some(camera) = node.camera() {
+            layout.cameras.push(*camera);
+        }
+
+        if let Some(light) = node.light() {
+            layout.lights.push(*light);
+        }
+
         if let Some(children) = node.children() {
             for child in children {
-                self.explore_node(child, core, blases, instances, primitives_blas_index, scene_data)?
+                self.explore_node(child, world_transforms, layout, primitives_blas_index);
             }
         }
+    }
+}
 
+/// When the BLASes of a [`SceneUpload`] are built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlasBuild {
+    /// As they are uploaded, waiting for the GPU.
+    OnUpload,
+    /// By the render graph of the next frame (see
+    /// [`vulkan_abstraction::Blas::new_deferred`]): the upload hands back the
+    /// build jobs in [`LoadedBlas::build_job`], and the copies of the geometry
+    /// they build from in [`LoadedScene::buffer_uploads`].
+    Deferred,
+}
+
+/// A BLAS a [`SceneUpload`] has yet to upload, with what it needs of the
+/// scene's layout.
+struct BlasUpload {
+    unique_key: vulkan_abstraction::gltf::PrimitiveUniqueKey,
+    material: vulkan_abstraction::gltf::Material,
+    emissive_triangles: Vec<vulkan_abstraction::gltf::EmissiveTriangle>,
+    /// `None` for a BLAS shared by the plain instances of its primitive.
+    deformation: Option<Deformation>,
+}
+
+/// The one instance of a deformed BLAS: see [`DeformedPrimitiveInstance`].
+struct Deformation {
+    node: usize,
+    skin: Option<usize>,
+    transform: vk::TransformMatrixKHR,
+    joint_matrices: Vec<na::Matrix4<f32>>,
+    morph_weights: Vec<f32>,
+}
+
+/// The GPU upload of a laid out scene, which can be spread over several
+/// calls: `Scene::load_into_gpu` runs it in one go, a background scene load a
+/// few megabytes per poll. Images come first, then the BLASes. The images
+/// must have gone through [`SceneData::prepare_images`].
+pub struct SceneUpload {
+    images: std::vec::IntoIter<vulkan_abstraction::gltf::Image>,
+    /// The images sampled by a texture whose sampler reads no mip maps: they
+    /// stay resident at full resolution.
+    full_resolution_images: HashSet<usize>,
+    blases: std::vec::IntoIter<BlasUpload>,
+    build: BlasBuild,
+    primitive_data_map: vulkan_abstraction::gltf::PrimitiveDataMap,
+    /// Everything uploaded so far.
+    loaded: LoadedScene,
+    total: usize,
+}
+
+impl SceneUpload {
+    pub fn new(scene: &Scene, scene_data: crate::SceneData, build: BlasBuild) -> Self {
+        let SceneLayout {
+            primitives,
+            instances,
+            instance_nodes,
+            deformed_instances,
+            cameras,
+            lights,
+        } = scene.layout();
+
+        let shared_blases = primitives.into_iter().map(|primitive| BlasUpload {
+            unique_key: primitive.unique_key,
+            material: primitive.material.clone(),
+            emissive_triangles: emissive_triangles(primitive),
+            deformation: None,
+        });
+        let deformed_blases = deformed_instances.into_iter().map(|instance| BlasUpload {
+            unique_key: instance.primitive.unique_key,
+            material: instance.primitive.material.clone(),
+            // the emissive triangles stay in the rest pose
+            emissive_triangles: emissive_triangles(instance.primitive),
+            deformation: Some(Deformation {
+                node: instance.node,
+                skin: instance.skin,
+                transform: instance.transform,
+                joint_matrices: instance.joint_matrices,
+                morph_weights: instance.morph_weights,
+            }),
+        });
+        let blases: Vec<_> = shared_blases.chain(deformed_blases).collect();
+
+        let sampler_descs = sampler_descs(&scene_data.samplers);
+        let full_resolution_images = full_resolution_images(&scene_data.textures, &sampler_descs);
+
+        Self {
+            total: scene_data.images.len() + blases.len(),
+            images: scene_data.images.into_iter(),
+            full_resolution_images,
+            blases: blases.into_iter(),
+            build,
+            primitive_data_map: scene_data.primitive_data_map,
+            loaded: LoadedScene {
+                blases: vec![],
+                instances,
+                instance_nodes,
+                deformed_blases: vec![],
+                textures: scene_data.textures,
+                sampler_descs,
+                images: vec![],
+                cameras,
+                lights,
+                buffer_uploads: vec![],
+                texture_uploads: vec![],
+            },
+        }
+    }
+
+    /// Upload assets until about `byte_budget` bytes of texels and geometry
+    /// are staged (at least one asset, however large). Only the geometry of
+    /// BLASes built [`BlasBuild::OnUpload`] is copied right away: the other
+    /// copies are left in [`LoadedScene`]. Returns whether everything is
+    /// uploaded.
+    pub fn upload_some(&mut self, core: &Rc<vulkan_abstraction::Core>, byte_budget: usize) -> SrResult<bool> {
+        let mut uploaded_bytes = 0;
+        while uploaded_bytes < byte_budget {
+            if let Some(image) = self.images.next() {
+                uploaded_bytes += image.byte_size();
+                let full_resolution = self.full_resolution_images.contains(&self.loaded.images.len());
+                let (texture, upload) = to_streamed_texture(core, image, full_resolution)?;
+                self.loaded.images.push(texture);
+                self.loaded.texture_uploads.push(upload);
+            } else if let Some(upload) = self.blases.next() {
+                let primitive_data = &self.primitive_data_map[&upload.unique_key];
+                uploaded_bytes +=
+                    std::mem::size_of_val(&primitive_data.vertices[..]) + std::mem::size_of_val(&primitive_data.indices[..]);
+                self.upload_blas(core, upload)?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(self.images.len() == 0 && self.blases.len() == 0)
+    }
+
+    fn upload_blas(&mut self, core: &Rc<vulkan_abstraction::Core>, upload: BlasUpload) -> SrResult<()> {
+        let primitive_data = &self.primitive_data_map[&upload.unique_key];
+
+        let Some(deformation) = upload.deformation else {
+            let (blas, build_job) = new_blas(
+                core,
+                primitive_data,
+                vulkan_abstraction::BuildType::Static,
+                upload.material.is_opaque(),
+                self.build,
+                &mut self.loaded.buffer_uploads,
+            )?;
+            self.loaded.blases.push(LoadedBlas {
+                blas,
+                material: upload.material,
+                emissive_triangles: upload.emissive_triangles,
+                build_job,
+            });
+            return Ok(());
+        };
+
+        let skinning = if primitive_data.skin_vertices.is_empty() || deformation.skin.is_none() {
+            None
+        } else {
+            let (skinning, uploads) = vulkan_abstraction::SkinnedGeometry::new(
+                Rc::clone(core),
+                &primitive_data.vertices,
+                &primitive_data.skin_vertices,
+                &deformation.joint_matrices,
+            )?;
+            self.loaded.buffer_uploads.extend(uploads);
+            Some(skinning)
+        };
+        let morphing = if primitive_data.morph_deltas.is_empty() {
+            None
+        } else {
+            let (morphing, uploads) = vulkan_abstraction::MorphedGeometry::new(
+                Rc::clone(core),
+                &primitive_data.vertices,
+                &primitive_data.morph_deltas,
+                &deformation.morph_weights,
+            )?;
+            self.loaded.buffer_uploads.extend(uploads);
+            Some(morphing)
+        };
+        let build_type = if skinning.is_some() || morphing.is_some() {
+            vulkan_abstraction::BuildType::RapidlyChanging
+        } else {
+            vulkan_abstraction::BuildType::Static
+        };
+
+        // starts out in the rest pose, until the first frame deforms it
+        let (blas, build_job) = new_blas(
+            core,
+            primitive_data,
+            build_type,
+            upload.material.is_opaque(),
+            self.build,
+            &mut self.loaded.buffer_uploads,
+        )?;
+
+        self.loaded.deformed_blases.push(LoadedDeformedBlas {
+            loaded: LoadedBlas {
+                blas,
+                material: upload.material,
+                emissive_triangles: upload.emissive_triangles,
+                build_job,
+            },
+            transform: deformation.transform,
+            node: deformation.node,
+            skin: deformation.skin,
+            skinning,
+            morphing,
+        });
         Ok(())
     }
+
+    /// How many of the scene's images and BLASes are uploaded, and how many
+    /// there are.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.total - self.images.len() - self.blases.len(), self.total)
+    }
+
+    /// What was uploaded: the whole scene once [`Self::upload_some`] returned
+    /// true.
+    pub fn finish(self) -> LoadedScene {
+        self.loaded
+    }
+}
+
+/// The sampler descriptions of a scene's glTF samplers, in order.
+fn sampler_descs(samplers: &[vulkan_abstraction::gltf::Sampler]) -> Vec<SamplerDesc> {
+    samplers
+        .iter()
+        .map(|sampler| {
+            let default = gltf::texture::MinFilter::Linear;
+
+            SamplerDesc {
+                min_filter: vk::Filter::from_gltf(sampler.min_filter.unwrap_or(default)),
+                mag_filter: vk::Filter::from_gltf(sampler.mag_filter.unwrap_or(gltf::texture::MagFilter::Linear)),
+                address_mode_u: vk::SamplerAddressMode::from_gltf(sampler.wrap_s_u),
+                address_mode_v: vk::SamplerAddressMode::from_gltf(sampler.wrap_t_v),
+                address_mode_w: vk::SamplerAddressMode::REPEAT,
+                mipmap_mode: vk::SamplerMipmapMode::from_gltf(sampler.min_filter.unwrap_or(default)),
+                // without a minification filter, the images are sampled trilinearly
+                mipmapped: !matches!(
+                    sampler.min_filter,
+                    Some(gltf::texture::MinFilter::Nearest | gltf::texture::MinFilter::Linear)
+                ),
+            }
+        })
+        .collect()
 }
 
-fn to_vk_image(
+/// The images sampled by a texture whose sampler reads no mip maps: they stay
+/// resident at full resolution.
+fn full_resolution_images(textures: &[vulkan_abstraction::gltf::Texture], sampler_descs: &[SamplerDesc]) -> HashSet<usize> {
+    textures
+        .iter()
+        .filter(|texture| texture.sampler.is_some_and(|sampler| !sampler_descs[sampler].mipmapped))
+        .flat_map(|texture| texture.source.into_iter().chain(texture.srgb_source))
+        .collect()
+}
+
+/// Upload a primitive's geometry and create its BLAS, built now or by the
+/// next frame's render graph. A deferred build leaves the geometry to the
+/// next frame too: its copies go to `buffer_uploads`.
+fn new_blas(
     core: &Rc<vulkan_abstraction::Core>,
+    primitive_data: &vulkan_abstraction::gltf::PrimitiveData,
+    build_type: vulkan_abstraction::BuildType,
+    opaque: bool,
+    build: BlasBuild,
+    buffer_uploads: &mut Vec<vulkan_abstraction::BufferUpload>,
+) -> SrResult<(vulkan_abstraction::Blas, Option<vulkan_abstraction::AsBuildJob>)> {
+    match build {
+        BlasBuild::OnUpload => {
+            let vertex_buffer =
+                vulkan_abstraction::VertexBuffer::new_for_blas_from_data(Rc::clone(core), &primitive_data.vertices)?;
+            let index_buffer = vulkan_abstraction::IndexBuffer::new_for_blas_from_data(Rc::clone(core), &primitive_data.indices)?;
+            let blas = vulkan_abstraction::Blas::new(Rc::clone(core), vertex_buffer, index_buffer, build_type, opaque)?;
+            Ok((blas, None))
+        }
+        BlasBuild::Deferred => {
+            let (vertex_buffer, vertex_upload) =
+                vulkan_abstraction::VertexBuffer::new_for_blas_with_upload(Rc::clone(core), &primitive_data.vertices)?;
+            let (index_buffer, index_upload) =
+                vulkan_abstraction::IndexBuffer::new_for_blas_with_upload(Rc::clone(core), &primitive_data.indices)?;
+            buffer_uploads.extend(vertex_upload.into_iter().chain(index_upload));
+            let (blas, build_job) =
+                vulkan_abstraction::Blas::new_deferred(Rc::clone(core), vertex_buffer, index_buffer, build_type, opaque)?;
+            Ok((blas, Some(build_job)))
+        }
+    }
+}
+
+/// A primitive's local-space emissive triangles, with its material's emission.
+fn emissive_triangles(primitive: &vulkan_abstraction::gltf::Primitive) -> Vec<vulkan_abstraction::gltf::EmissiveTriangle> {
+    let material = &primitive.material;
+    let emission = [
+        material.emissive_factor[0] * material.emissive_strength,
+        material.emissive_factor[1] * material.emissive_strength,
+        material.emissive_factor[2] * material.emissive_strength,
+        0.0,
+    ];
+
+    primitive
+        .local_emissive_triangles
+        .iter()
+        .map(|local_tri| vulkan_abstraction::gltf::EmissiveTriangle {
+            v0: [local_tri[0].x, local_tri[0].y, local_tri[0].z, 0.0],
+            v1: [local_tri[1].x, local_tri[1].y, local_tri[1].z, 0.0],
+            v2: [local_tri[2].x, local_tri[2].y, local_tri[2].z, 0.0],
+            emission,
+        })
+        .collect()
+}
+
+/// `image` with its texels as the mip levels it is uploaded as: see
+/// [`SceneData::prepare_images`]. Decoded images get their mip chain built,
+/// unless they stay at `full_resolution`, so the levels can be streamed from
+/// memory.
+fn prepare_image(
     image: vulkan_abstraction::gltf::Image,
-) -> SrResult<vulkan_abstraction::Image> {
-    let format = vk::Format::from_gltf(image.format);
+    full_resolution: bool,
+    formats: &TextureFormatSupport,
+) -> SrResult<vulkan_abstraction::gltf::Image> {
+    let pixels = match image.pixels {
+        vulkan_abstraction::gltf::ImagePixels::Decoded { format, raw_data } => {
+            let upload_format = pick_upload_format(formats, format, image.encoding)?;
+            let raw_data = vulkan_abstraction::gltf::convert_decoded(format, &raw_data, upload_format).ok_or_else(|| {
+                SrError::new_custom(format!("gltf image: cannot convert {format:?} texels to {upload_format:?}"))
+            })?;
+            let levels = if full_resolution {
+                // sampled without mip maps: the base level is all the image needs
+                vec![raw_data]
+            } else {
+                let format = with_encoding(upload_format, image.encoding);
+                vulkan_abstraction::gltf::mip_chain(raw_data, image.width, image.height, format)
+            };
+            vulkan_abstraction::gltf::ImagePixels::Levels {
+                format: upload_format,
+                levels,
+            }
+        }
+        vulkan_abstraction::gltf::ImagePixels::Uastc { levels } => {
+            let (format, levels) =
+                vulkan_abstraction::gltf::Image::transcode_uastc(&levels, image.width, image.height, formats.bc)?;
+            vulkan_abstraction::gltf::ImagePixels::Levels { format, levels }
+        }
+        levels @ vulkan_abstraction::gltf::ImagePixels::Levels { .. } => levels,
+    };
+
+    Ok(vulkan_abstraction::gltf::Image { pixels, ..image })
+}
 
-    let image = vulkan_abstraction::Image::new_from_data(
+/// Create a texture from a prepared `image`, whose larger mip levels stream
+/// in on demand, or which stays whole with `full_resolution`. Returns it with
+/// the upload of its resident levels.
+fn to_streamed_texture(
+    core: &Rc<vulkan_abstraction::Core>,
+    image: vulkan_abstraction::gltf::Image,
+    full_resolution: bool,
+) -> SrResult<(vulkan_abstraction::StreamedTexture, vulkan_abstraction::TextureUpload)> {
+    let extent = vk::Extent3D {
+        width: image.width as u32,
+        height: image.height as u32,
+        depth: 1,
+    };
+
+    let vulkan_abstraction::gltf::ImagePixels::Levels { format, levels } = image.pixels else {
+        return Err(SrError::new_custom(
+            "gltf image: uploaded before SceneData::prepare_images".to_string(),
+        ));
+    };
+
+    vulkan_abstraction::StreamedTexture::new(
         Rc::clone(core),
-        image.raw_data,
-        vk::Extent3D {
-            width: image.width as u32,
-            height: image.height as u32,
-            depth: 1,
-        },
-        format,
-        vk::ImageTiling::OPTIMAL,
-        gpu_allocator::MemoryLocation::GpuOnly,
-        vk::ImageUsageFlags::SAMPLED,
+        levels,
+        extent,
+        with_encoding(format, image.encoding),
+        full_resolution,
         "gltf image",
-    )?;
+    )
+}
+
+/// The format to upload the texels of a decoded `format` image as, read as
+/// `encoding`: the first of [`upload_formats`] the device samples with linear
+/// filtering and can blit mip levels of, else the first it samples at all.
+fn pick_upload_format(
+    formats: &TextureFormatSupport,
+    format: gltf::image::Format,
+    encoding: vulkan_abstraction::gltf::TexelEncoding,
+) -> SrResult<vk::Format> {
+    let candidates = upload_formats(format)
+        .into_iter()
+        // 16-bit channels have no sRGB formats: color textures fall back to 8 bits to be decoded
+        .filter(|&candidate| {
+            encoding == vulkan_abstraction::gltf::TexelEncoding::Linear
+                || with_encoding(candidate, encoding) != candidate
+                || matches!(candidate, vk::Format::R32G32B32_SFLOAT | vk::Format::R32G32B32A32_SFLOAT)
+        })
+        .map(|candidate| with_encoding(candidate, encoding))
+        .map(|candidate| (candidate, formats.features(candidate)))
+        .collect::<Vec<_>>();
+
+    let preferred = vk::FormatFeatureFlags::SAMPLED_IMAGE
+        | vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR
+        | vk::FormatFeatureFlags::BLIT_SRC
+        | vk::FormatFeatureFlags::BLIT_DST;
+
+    candidates
+        .iter()
+        .find(|(_, features)| features.contains(preferred))
+        .or_else(|| {
+            candidates
+                .iter()
+                .find(|(_, features)| features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE))
+        })
+        .map(|&(candidate, _)| candidate)
+        .ok_or_else(|| {
+            SrError::new_custom(format!(
+                "gltf image: the device cannot sample {format:?} images as any of {:?}",
+                candidates.iter().map(|(candidate, _)| candidate).collect::<Vec<_>>()
+            ))
+        })
+}
+
+/// The formats the texels of a decoded `format` image can be uploaded as, best
+/// first: the image's own layout, then wider ones GPUs support more commonly,
+/// down to RGBA8 (RGBA32F for float images). Gray images are always widened,
+/// since the shaders read colors from the first three channels.
+fn upload_formats(format: gltf::image::Format) -> Vec<vk::Format> {
+    let native = vk::Format::from_gltf(format);
+    match format {
+        gltf::image::Format::R8 | gltf::image::Format::R8G8 => vec![vk::Format::R8G8B8A8_UNORM],
+        gltf::image::Format::R8G8B8 => vec![native, vk::Format::R8G8B8A8_UNORM],
+        gltf::image::Format::R8G8B8A8 => vec![native],
+        gltf::image::Format::R16 | gltf::image::Format::R16G16 => {
+            vec![vk::Format::R16G16B16A16_UNORM, vk::Format::R8G8B8A8_UNORM]
+        }
+        gltf::image::Format::R16G16B16 => vec![native, vk::Format::R16G16B16A16_UNORM, vk::Format::R8G8B8A8_UNORM],
+        gltf::image::Format::R16G16B16A16 => vec![native, vk::Format::R8G8B8A8_UNORM],
+        gltf::image::Format::R32G32B32FLOAT => vec![native, vk::Format::R32G32B32A32_SFLOAT],
+        gltf::image::Format::R32G32B32A32FLOAT => vec![native],
+    }
+}
 
-    Ok(image)
+/// `format`, or its `_SRGB` / `_UNORM` twin, for texels encoded as `encoding`:
+/// the sampler decodes sRGB colors to linear. Formats without a twin (16-bit,
+/// float) are kept as they are.
+fn with_encoding(format: vk::Format, encoding: vulkan_abstraction::gltf::TexelEncoding) -> vk::Format {
+    const TWINS: [(vk::Format, vk::Format); 9] = [
+        (vk::Format::R8_UNORM, vk::Format::R8_SRGB),
+        (vk::Format::R8G8_UNORM, vk::Format::R8G8_SRGB),
+        (vk::Format::R8G8B8_UNORM, vk::Format::R8G8B8_SRGB),
+        (vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB),
+        (vk::Format::BC1_RGB_UNORM_BLOCK, vk::Format::BC1_RGB_SRGB_BLOCK),
+        (vk::Format::BC1_RGBA_UNORM_BLOCK, vk::Format::BC1_RGBA_SRGB_BLOCK),
+        (vk::Format::BC2_UNORM_BLOCK, vk::Format::BC2_SRGB_BLOCK),
+        (vk::Format::BC3_UNORM_BLOCK, vk::Format::BC3_SRGB_BLOCK),
+        (vk::Format::BC7_UNORM_BLOCK, vk::Format::BC7_SRGB_BLOCK),
+    ];
+
+    TWINS
+        .iter()
+        .find(|(unorm, srgb)| format == *unorm || format == *srgb)
+        .map_or(format, |&(unorm, srgb)| match encoding {
+            vulkan_abstraction::gltf::TexelEncoding::Srgb => srgb,
+            vulkan_abstraction::gltf::TexelEncoding::Linear => unorm,
+        })
 }
 
 // Because of the orphan rule of rust
@@ -207,10 +838,11 @@ impl FromGltf<gltf::image::Format> for vk::Format {
             gltf::image::Format::R8G8 => vk::Format::R8G8_UNORM,
             gltf::image::Format::R8G8B8 => vk::Format::R8G8B8_UNORM,
             gltf::image::Format::R8G8B8A8 => vk::Format::R8G8B8A8_UNORM,
-            gltf::image::Format::R16 => vk::Format::R16_SFLOAT,
-            gltf::image::Format::R16G16 => vk::Format::R16G16_SFLOAT,
-            gltf::image::Format::R16G16B16 => vk::Format::R16G16B16_SFLOAT,
-            gltf::image::Format::R16G16B16A16 => vk::Format::R16G16B16A16_SFLOAT,
+            // 16-bit PNGs store normalized integers
+            gltf::image::Format::R16 => vk::Format::R16_UNORM,
+            gltf::image::Format::R16G16 => vk::Format::R16G16_UNORM,
+            gltf::image::Format::R16G16B16 => vk::Format::R16G16B16_UNORM,
+            gltf::image::Format::R16G16B16A16 => vk::Format::R16G16B16A16_UNORM,
             gltf::image::Format::R32G32B32FLOAT => vk::Format::R32G32B32_SFLOAT,
             gltf::image::Format::R32G32B32A32FLOAT => vk::Format::R32G32B32A32_SFLOAT,
         }
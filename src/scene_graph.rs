@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use nalgebra as na;
+
+use crate::error::{SrError, SrResult};
+use crate::vulkan_abstraction::gltf::{Node, Trs};
+
+/// A node of a [`SceneGraph`].
+#[derive(Clone, Debug)]
+pub struct SceneGraphNode {
+    /// Index of the node in the glTF document.
+    index: usize,
+    name: Option<String>,
+    /// glTF index of the parent, `None` for the scene's roots.
+    parent: Option<usize>,
+    /// glTF indices of the children.
+    children: Vec<usize>,
+    local_trs: Trs,
+    local_transform: na::Matrix4<f32>,
+    world_transform: na::Matrix4<f32>,
+}
+
+impl SceneGraphNode {
+    /// Index of the node in the glTF document: the node
+    /// [`crate::SceneLoad::instance_nodes`] refers to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn parent(&self) -> Option<usize> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[usize] {
+        &self.children
+    }
+
+    /// The transform relative to the parent, decomposed.
+    pub fn local_trs(&self) -> &Trs {
+        &self.local_trs
+    }
+
+    /// The transform relative to the parent (the authored matrix until the
+    /// node is edited, shear included).
+    pub fn local_transform(&self) -> &na::Matrix4<f32> {
+        &self.local_transform
+    }
+
+    pub fn world_transform(&self) -> &na::Matrix4<f32> {
+        &self.world_transform
+    }
+}
+
+/// The node hierarchy of a loaded scene: names, parent links, local and world
+/// transforms. Editing a node's local transform keeps the world transforms of
+/// its subtree up to date, and [`Self::instances`] turns them into the
+/// instance list `Renderer::render` takes.
+#[derive(Clone, Debug, Default)]
+pub struct SceneGraph {
+    /// Parents come before their children.
+    nodes: Vec<SceneGraphNode>,
+    /// glTF index → position in `nodes`.
+    positions: HashMap<usize, usize>,
+    roots: Vec<usize>,
+}
+
+impl SceneGraph {
+    /// The graph of the scene whose root nodes are `roots`.
+    pub fn new(roots: &[Node]) -> Self {
+        let mut graph = Self::default();
+        for root in roots {
+            graph.roots.push(root.index());
+            graph.add_subtree(root, None, &na::Matrix4::identity());
+        }
+        graph
+    }
+
+    fn add_subtree(&mut self, node: &Node, parent: Option<usize>, parent_transform: &na::Matrix4<f32>) {
+        let world_transform = parent_transform * node.local_transform();
+        let children = node.children().as_deref().unwrap_or_default();
+
+        self.positions.insert(node.index(), self.nodes.len());
+        self.nodes.push(SceneGraphNode {
+            index: node.index(),
+            name: node.name().map(String::from),
+            parent,
+            children: children.iter().map(Node::index).collect(),
+            local_trs: *node.local_trs(),
+            local_transform: *node.local_transform(),
+            world_transform,
+        });
+
+        for child in children {
+            self.add_subtree(child, Some(node.index()), &world_transform);
+        }
+    }
+
+    /// Every node, parents before their children.
+    pub fn nodes(&self) -> &[SceneGraphNode] {
+        &self.nodes
+    }
+
+    /// glTF indices of the scene's root nodes.
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// The node with this glTF index, if it's part of the scene.
+    pub fn node(&self, index: usize) -> Option<&SceneGraphNode> {
+        self.positions.get(&index).map(|&position| &self.nodes[position])
+    }
+
+    /// The first node named `name`.
+    pub fn find(&self, name: &str) -> Option<&SceneGraphNode> {
+        self.nodes.iter().find(|node| node.name() == Some(name))
+    }
+
+    /// Move the node with glTF index `index` relative to its parent, and
+    /// recompute the world transforms of the node and its descendants.
+    pub fn set_local_trs(&mut self, index: usize, trs: Trs) -> SrResult<()> {
+        let position = *self
+            .positions
+            .get(&index)
+            .ok_or_else(|| SrError::new_custom(format!("scene graph: no node with index {index}")))?;
+
+        let node = &mut self.nodes[position];
+        node.local_trs = trs;
+        node.local_transform = trs.to_matrix();
+
+        self.update_world_transforms(index);
+        Ok(())
+    }
+
+    /// Recompute the world transforms of the subtree rooted at `index`, from
+    /// its parent's.
+    fn update_world_transforms(&mut self, index: usize) {
+        let position = self.positions[&index];
+        let parent_transform = match self.nodes[position].parent {
+            Some(parent) => self.nodes[self.positions[&parent]].world_transform,
+            None => na::Matrix4::identity(),
+        };
+
+        let node = &mut self.nodes[position];
+        node.world_transform = parent_transform * node.local_transform;
+
+        for child in self.nodes[position].children.clone() {
+            self.update_world_transforms(child);
+        }
+    }
+
+    /// The world transform of every node, by glTF index.
+    pub fn world_transforms(&self) -> HashMap<usize, na::Matrix4<f32>> {
+        self.nodes.iter().map(|node| (node.index, node.world_transform)).collect()
+    }
+
+    /// `load.instances` with every transform replaced by its node's current
+    /// world transform.
+    pub fn instances<K: Copy>(&self, load: &crate::SceneLoad<K>) -> Vec<(K, Vec<vk::TransformMatrixKHR>)> {
+        load.posed_instances(&self.world_transforms())
+    }
+
+    /// The joint matrices of every skinned instance of `load` for the current
+    /// world transforms, ready for `Renderer::set_joint_matrices`.
+    pub fn joint_matrices<K: Copy>(&self, load: &crate::SceneLoad<K>) -> Vec<(K, Vec<na::Matrix4<f32>>)> {
+        load.posed_joint_matrices(&self.world_transforms())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    /// Moving the root of animated.gltf moves its child, and the instance with
+    /// it.
+    #[test]
+    fn scene_graph_edits_propagate_to_the_children() {
+        let (scene, _) = crate::vulkan_abstraction::gltf::load_test_scene("animated.gltf");
+        let mut graph = scene.graph();
+        assert_eq!(graph.roots(), &[0]);
+
+        let body = graph.find("body").expect("the child is named");
+        assert_eq!((body.index(), body.parent()), (1, Some(0)));
+        assert_eq!(body.world_transform()[(1, 3)], 1.0);
+        assert_eq!(graph.node(0).and_then(|root| root.name()), Some("root"));
+
+        let mut trs = *graph.node(0).unwrap().local_trs();
+        trs.translation.x = 5.0;
+        graph.set_local_trs(0, trs).unwrap();
+        assert!(graph.set_local_trs(7, trs).is_err());
+
+        let body = graph.node(1).unwrap();
+        assert_eq!(
+            body.local_transform()[(1, 3)],
+            1.0,
+            "the child's local transform is left alone"
+        );
+        assert_eq!((body.world_transform()[(0, 3)], body.world_transform()[(1, 3)]), (5.0, 1.0));
+
+        let instances = graph.instances(&crate::SceneLoad::for_test(&scene));
+        assert_eq!(instances[0].1[0].matrix[3], 5.0);
+        assert_eq!(instances[0].1[0].matrix[7], 1.0);
+    }
+}
@@ -0,0 +1,128 @@
+use std::rc::Rc;
+use std::sync::mpsc;
+
+use crate::error::{SrError, SrResult};
+use crate::{BlasBuild, LoadedScene, Scene, SceneData, SceneLoad, SceneUpload, vulkan_abstraction};
+
+/// About how many bytes of texels and geometry
+/// [`crate::Renderer::poll_scene_load`] stages per call.
+pub const SCENE_UPLOAD_BUDGET: usize = 32 * 1024 * 1024;
+
+/// Where a background scene load stands, as
+/// [`crate::Renderer::poll_scene_load`] reports it.
+pub enum SceneLoadStatus<K> {
+    /// A worker thread is still reading the glTF document and decoding its
+    /// images.
+    Decoding,
+    /// `uploaded` of the scene's `total` images and BLASes are staged for the
+    /// GPU.
+    Uploading { uploaded: usize, total: usize },
+    /// The scene is loaded, as [`crate::Renderer::load_scene`] would have.
+    Ready(SceneLoad<K>),
+}
+
+/// A scene loaded in the background: started by
+/// [`crate::Renderer::load_gltf_async`] and driven by
+/// [`crate::Renderer::poll_scene_load`], typically once per frame. Dropping
+/// it gives up on the load: what was uploaded is freed, and the worker
+/// thread's result is discarded.
+pub struct PendingSceneLoad {
+    state: LoadState,
+}
+
+enum LoadState {
+    Decoding(mpsc::Receiver<SrResult<(Scene, SceneData)>>),
+    Uploading {
+        scene: Scene,
+        skipped_primitives: Vec<vulkan_abstraction::gltf::SkippedPrimitive>,
+        upload: SceneUpload,
+    },
+    /// The scene was handed out, or failed to load.
+    Finished,
+}
+
+impl PendingSceneLoad {
+    /// Run `decode` on a worker thread, and upload the scene it returns once
+    /// polled.
+    pub fn spawn(decode: impl FnOnce() -> SrResult<(Scene, SceneData)> + Send + 'static) -> SrResult<Self> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::Builder::new()
+            .name("scene loader".to_string())
+            .spawn(move || {
+                // fails when the load was dropped: nobody wants the scene anymore
+                let _ = sender.send(decode());
+            })
+            .map_err(|e| SrError::new_custom(format!("cannot spawn the scene loader thread: {e}")))?;
+
+        Ok(Self {
+            state: LoadState::Decoding(receiver),
+        })
+    }
+
+    /// `(uploaded, total)` images and BLASes of the scene, `None` while it's
+    /// decoded.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        match &self.state {
+            LoadState::Uploading { upload, .. } => Some(upload.progress()),
+            LoadState::Decoding(_) | LoadState::Finished => None,
+        }
+    }
+
+    /// Take the decoded scene if the worker thread is done with it, then
+    /// upload about `byte_budget` bytes of it, building its BLASes in the
+    /// next frame's render graph. Returns the scene once it's all uploaded;
+    /// after that, or after an error, the load is over.
+    pub(crate) fn advance(
+        &mut self,
+        core: &Rc<vulkan_abstraction::Core>,
+        byte_budget: usize,
+    ) -> SrResult<Option<(Scene, LoadedScene, Vec<vulkan_abstraction::gltf::SkippedPrimitive>)>> {
+        let advanced = self.try_advance(core, byte_budget);
+        if advanced.is_err() {
+            self.state = LoadState::Finished;
+        }
+        advanced
+    }
+
+    fn try_advance(
+        &mut self,
+        core: &Rc<vulkan_abstraction::Core>,
+        byte_budget: usize,
+    ) -> SrResult<Option<(Scene, LoadedScene, Vec<vulkan_abstraction::gltf::SkippedPrimitive>)>> {
+        if let LoadState::Decoding(receiver) = &self.state {
+            let (scene, mut scene_data) = match receiver.try_recv() {
+                Ok(decoded) => decoded?,
+                Err(mpsc::TryRecvError::Empty) => return Ok(None),
+                Err(mpsc::TryRecvError::Disconnected) => {
+                    return Err(SrError::new_custom("the scene loader thread panicked".to_string()));
+                }
+            };
+
+            let skipped_primitives = std::mem::take(&mut scene_data.skipped_primitives);
+            let upload = SceneUpload::new(&scene, scene_data, BlasBuild::Deferred);
+            self.state = LoadState::Uploading {
+                scene,
+                skipped_primitives,
+                upload,
+            };
+        }
+
+        let LoadState::Uploading { upload, .. } = &mut self.state else {
+            return Err(SrError::new_custom(
+                "poll_scene_load: the scene was already returned, or failed to load".to_string(),
+            ));
+        };
+        if !upload.upload_some(core, byte_budget)? {
+            return Ok(None);
+        }
+
+        match std::mem::replace(&mut self.state, LoadState::Finished) {
+            LoadState::Uploading {
+                scene,
+                skipped_primitives,
+                upload,
+            } => Ok(Some((scene, upload.finish(), skipped_primitives))),
+            LoadState::Decoding(_) | LoadState::Finished => unreachable!("the load is uploading"),
+        }
+    }
+}
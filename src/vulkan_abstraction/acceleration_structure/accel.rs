@@ -63,7 +63,6 @@ impl AsBuildJob {
 pub struct AccelerationStructure {
     core: Rc<vulkan_abstraction::Core>,
     handle: vk::AccelerationStructureKHR,
-    #[allow(dead_code)]
     buffer: vulkan_abstraction::GpuOnlyBuffer,
     device_address: vk::DeviceAddress,
 }
@@ -211,6 +210,24 @@ impl AccelerationStructure {
     /// while the update job is in flight).
     #[allow(dead_code)]
     pub fn update(&self, inputs: AsBuildInputs) -> SrResult<AsBuildJob> {
+        self.in_place_job(inputs, vk::BuildAccelerationStructureModeKHR::UPDATE)
+    }
+
+    /// Prepare a full BUILD of `inputs` into this structure's own memory,
+    /// deferred like [`Self::build`] — unlike it, the handle and device address
+    /// stay the same, so instances referencing them need no patching. For a
+    /// structure rebuilt often (e.g. a skinned BLAS), this restores the trace
+    /// quality updates lose without reallocating. Fails if `inputs` needs more
+    /// memory than the structure has, i.e. if the geometry grew.
+    ///
+    /// `&self` for the same reason as [`Self::update`].
+    pub fn rebuild_in_place(&self, inputs: AsBuildInputs) -> SrResult<AsBuildJob> {
+        self.in_place_job(inputs, vk::BuildAccelerationStructureModeKHR::BUILD)
+    }
+
+    /// The deferred job behind [`Self::update`] (src = dst = this handle) and
+    /// [`Self::rebuild_in_place`] (dst = this handle).
+    fn in_place_job(&self, inputs: AsBuildInputs, mode: vk::BuildAccelerationStructureModeKHR) -> SrResult<AsBuildJob> {
         assert_eq!(inputs.geometries.len(), inputs.ranges.len());
         let AsBuildInputs {
             ty,
@@ -223,7 +240,7 @@ impl AccelerationStructure {
             let incomplete_build_geometry_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
                 .geometries(&geometries)
                 .flags(flags)
-                .mode(vk::BuildAccelerationStructureModeKHR::UPDATE)
+                .mode(mode)
                 .ty(ty);
             let mut size_info = vk::AccelerationStructureBuildSizesInfoKHR::default();
             let primitive_counts = ranges.iter().map(|i| i.primitive_count).collect::<Vec<_>>();
@@ -238,6 +255,19 @@ impl AccelerationStructure {
             size_info
         };
 
+        if size_info.acceleration_structure_size > self.buffer.byte_size() {
+            return Err(SrError::new_custom(format!(
+                "acceleration structure needs {} bytes but was allocated with {}",
+                size_info.acceleration_structure_size,
+                self.buffer.byte_size()
+            )));
+        }
+
+        let scratch_size = if mode == vk::BuildAccelerationStructureModeKHR::UPDATE {
+            size_info.update_scratch_size
+        } else {
+            size_info.build_scratch_size
+        };
         let scratch_alignment = self
             .core
             .device()
@@ -245,15 +275,21 @@ impl AccelerationStructure {
             .min_acceleration_structure_scratch_offset_alignment as u64;
 
         let handle = self.handle;
+        // an update refits the structure in place: it is its own source
+        let src = if mode == vk::BuildAccelerationStructureModeKHR::UPDATE {
+            handle
+        } else {
+            vk::AccelerationStructureKHR::null()
+        };
         let record_core = Rc::clone(&self.core);
         let record = Box::new(
             move |cmd_buf: vk::CommandBuffer, scratch: &vulkan_abstraction::GpuOnlyBuffer| {
                 let build_geometry_info = vk::AccelerationStructureBuildGeometryInfoKHR::default()
                     .geometries(&geometries)
                     .flags(flags)
-                    .mode(vk::BuildAccelerationStructureModeKHR::UPDATE)
+                    .mode(mode)
                     .ty(ty)
-                    .src_acceleration_structure(handle)
+                    .src_acceleration_structure(src)
                     .dst_acceleration_structure(handle)
                     .scratch_data(vk::DeviceOrHostAddressKHR {
                         device_address: scratch.get_device_address(),
@@ -270,7 +306,7 @@ impl AccelerationStructure {
         );
 
         Ok(AsBuildJob {
-            scratch_size: size_info.build_scratch_size,
+            scratch_size,
             scratch_alignment,
             record,
         })
@@ -134,13 +134,17 @@ impl Blas {
     /// the vertex_buffer is assumed to have a vec3 position attribute as its first (not necessarily the only) attribute in memory.
     /// Emissive triangles are no longer tracked here — the `ResourceManager` owns
     /// the per-BLAS emissive triangle slots.
+    ///
+    /// Geometry that isn't `opaque` runs the any-hit shader (alpha testing and
+    /// stochastic transparency); opaque geometry skips it.
     pub fn new(
         core: Rc<vulkan_abstraction::Core>,
         vertex_buffer: VertexBuffer,
         index_buffer: IndexBuffer,
         build_type: BuildType,
+        opaque: bool,
     ) -> SrResult<Self> {
-        Self::new_with_build_flags(core, vertex_buffer, index_buffer, Self::build_flags(build_type))
+        Self::new_with_build_flags(core, vertex_buffer, index_buffer, Self::build_flags(build_type), opaque)
     }
 
     /// Map a [`BuildType`] to its Vulkan build flags. PREFER_FAST_BUILD ->
@@ -169,9 +173,14 @@ impl Blas {
         vertex_buffer: VertexBuffer,
         index_buffer: IndexBuffer,
         flags: vk::BuildAccelerationStructureFlagsKHR,
+        opaque: bool,
     ) -> SrResult<Self> {
         let desc = BlasDesc {
-            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(&vertex_buffer, &index_buffer))],
+            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(
+                &vertex_buffer,
+                &index_buffer,
+                opaque,
+            ))],
             flags,
         };
 
@@ -205,10 +214,15 @@ impl Blas {
         vertex_buffer: VertexBuffer,
         index_buffer: IndexBuffer,
         build_type: BuildType,
+        opaque: bool,
     ) -> SrResult<(Self, AsBuildJob)> {
         let flags = Self::build_flags(build_type);
         let desc = BlasDesc {
-            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(&vertex_buffer, &index_buffer))],
+            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(
+                &vertex_buffer,
+                &index_buffer,
+                opaque,
+            ))],
             flags,
         };
 
@@ -261,9 +275,31 @@ impl Blas {
         self.op
     }
 
-    /// Build a [`TriangleGeometryDesc`] from a vertex + index buffer, using the
-    /// fixed geometry flags the renderer's triangle meshes use.
-    fn triangle_desc(vertex_buffer: &VertexBuffer, index_buffer: &IndexBuffer) -> TriangleGeometryDesc {
+    /// The deferred job carrying out `op` (from [`Self::plan_op`]) on this BLAS
+    /// in place, from the current contents of its geometry buffers — for
+    /// geometry rewritten on the GPU, like a skinned mesh's. Updates refit;
+    /// both rebuilds build into the structure's own memory with its original
+    /// flags, so its device address never changes. Requires `ALLOW_UPDATE`.
+    pub fn refit_job(&self, op: OpType) -> SrResult<AsBuildJob> {
+        if !self.desc.flags.contains(vk::BuildAccelerationStructureFlagsKHR::ALLOW_UPDATE) {
+            return Err(SrError::new_custom("The structure is not updatable".to_string()));
+        }
+
+        match op {
+            OpType::Update => self.accel.update(self.desc.realize()),
+            OpType::FastBuild | OpType::SlowBuild => self.accel.rebuild_in_place(self.desc.realize()),
+        }
+    }
+
+    /// Build a [`TriangleGeometryDesc`] from a vertex + index buffer. Only
+    /// geometry that isn't `opaque` invokes the any-hit shader, once per
+    /// primitive.
+    fn triangle_desc(vertex_buffer: &VertexBuffer, index_buffer: &IndexBuffer, opaque: bool) -> TriangleGeometryDesc {
+        let mut flags = vk::GeometryFlagsKHR::NO_DUPLICATE_ANY_HIT_INVOCATION;
+        if opaque {
+            flags |= vk::GeometryFlagsKHR::OPAQUE;
+        }
+
         TriangleGeometryDesc {
             vertex_address: vertex_buffer.get_device_address(),
             vertex_stride: vertex_buffer.stride() as u64,
@@ -272,18 +308,30 @@ impl Blas {
             index_address: index_buffer.get_device_address(),
             index_type: index_buffer.index_type(),
             primitive_count: (index_buffer.len() / 3) as u32,
-            //TODO why always opaque?
-            flags: vk::GeometryFlagsKHR::OPAQUE | vk::GeometryFlagsKHR::NO_DUPLICATE_ANY_HIT_INVOCATION,
+            flags,
         }
     }
 
+    /// Whether the geometry skips the any-hit shader.
+    pub fn is_opaque(&self) -> bool {
+        self.desc.geometries.iter().all(|source| match source {
+            GeometrySource::Triangles(tri) => tri.flags.contains(vk::GeometryFlagsKHR::OPAQUE),
+        })
+    }
+
     pub fn state(&self) -> &AsState {
         &self.state
     }
 
     #[allow(unused)]
     pub fn rebuild(&mut self, vertex_buffer: VertexBuffer, index_buffer: IndexBuffer, build_type: BuildType) -> SrResult<()> {
-        *self = Self::new(Rc::clone(self.accel.core()), vertex_buffer, index_buffer, build_type)?;
+        *self = Self::new(
+            Rc::clone(self.accel.core()),
+            vertex_buffer,
+            index_buffer,
+            build_type,
+            self.is_opaque(),
+        )?;
         log::debug!("BLAS rebuilt");
         Ok(())
     }
@@ -296,7 +344,11 @@ impl Blas {
 
         // Same geometry count / layout, new buffer contents.
         let desc = BlasDesc {
-            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(&vertex_buffer, &index_buffer))],
+            geometries: vec![GeometrySource::Triangles(Self::triangle_desc(
+                &vertex_buffer,
+                &index_buffer,
+                self.is_opaque(),
+            ))],
             flags: self.desc.flags,
         };
         self.accel.update_sync(desc.realize())?;
@@ -8,6 +8,13 @@ use std::rc::Rc;
 pub struct GpuOnlyBuffer {
     raw: RawBuffer,
 }
+
+/// The copy filling a buffer made by [`GpuOnlyBuffer::new_with_upload`], with
+/// the staging buffer it reads, to keep alive until the copy has run.
+pub struct BufferUpload {
+    pub staging_buffer: RawBuffer,
+    pub copy: (vk::Buffer, vk::Buffer, vk::BufferCopy),
+}
 crate::impl_buffer_trait!(GpuOnlyBuffer);
 
 impl GpuOnlyBuffer {
@@ -72,6 +79,41 @@ impl GpuOnlyBuffer {
 
         Ok(gpu_buffer)
     }
+
+    /// Create a buffer like [`Self::new_from_data`] without uploading `data`.
+    /// Returns it with the upload filling it, for the graphics queue to record
+    /// before anything reads the buffer (see
+    /// `RenderGraph::add_prologue_buffer_copies`); there is none for empty
+    /// `data`.
+    pub fn new_with_upload<T>(
+        core: Rc<vulkan_abstraction::Core>,
+        data: &[T],
+        buffer_usage_flags: vk::BufferUsageFlags,
+        name: &'static str,
+    ) -> SrResult<(Self, Option<BufferUpload>)>
+    where
+        T: Copy,
+    {
+        if data.is_empty() {
+            return Ok((Self::new_null(core), None));
+        }
+
+        let staging_buffer = StagingBuffer::new_temp_from_data(Rc::clone(&core), data)?;
+        let gpu_buffer = Self::new::<T>(core, data.len() as vk::DeviceSize, buffer_usage_flags, name)?;
+        let copy = (
+            staging_buffer.inner(),
+            gpu_buffer.inner(),
+            vk::BufferCopy::default().size(staging_buffer.byte_size()),
+        );
+
+        Ok((
+            gpu_buffer,
+            Some(BufferUpload {
+                staging_buffer: staging_buffer.into(),
+                copy,
+            }),
+        ))
+    }
 }
 
 impl From<GpuOnlyBuffer> for RawBuffer {
@@ -38,6 +38,36 @@ impl IndexBuffer {
             idx_type,
         })
     }
+    //build an index buffer for usage in a blas, with the upload filling it (see GpuOnlyBuffer::new_with_upload)
+    pub fn new_for_blas_with_upload<T>(
+        core: Rc<vulkan_abstraction::Core>,
+        data: &[T],
+    ) -> SrResult<(Self, Option<vulkan_abstraction::BufferUpload>)>
+    where
+        T: 'static + Copy,
+    {
+        let usage_flags = vk::BufferUsageFlags::INDEX_BUFFER
+            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+            | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
+
+        let idx_type = match get_index_type::<T>() {
+            Some(idx_type) => idx_type,
+            None => {
+                return Err(SrError::new_custom(
+                    "attempting to construct IndexBuffer from invalid type".to_string(),
+                ));
+            }
+        };
+
+        let (buffer, upload) = GpuOnlyBuffer::new_with_upload(core, data, usage_flags, "index buffer for BLAS usage")?;
+        let index_buffer = Self {
+            buffer,
+            len: data.len(),
+            idx_type,
+        };
+
+        Ok((index_buffer, upload))
+    }
     pub fn new_for_blas<T>(core: Rc<vulkan_abstraction::Core>, len: vk::DeviceSize) -> SrResult<Self>
     where
         T: 'static,
@@ -255,3 +255,9 @@ impl<T> HostAccessibleBuffer<T> for StagingBuffer<T> {
         (self.raw.byte_size as usize) / std::mem::size_of::<T>()
     }
 }
+
+impl<T> From<StagingBuffer<T>> for RawBuffer {
+    fn from(value: StagingBuffer<T>) -> Self {
+        value.raw
+    }
+}
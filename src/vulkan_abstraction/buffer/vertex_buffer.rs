@@ -25,6 +25,27 @@ impl VertexBuffer {
         })
     }
 
+    //build a vertex buffer for usage in a blas, with the upload filling it (see GpuOnlyBuffer::new_with_upload)
+    pub fn new_for_blas_with_upload<T: Copy>(
+        core: Rc<vulkan_abstraction::Core>,
+        data: &[T],
+    ) -> SrResult<(Self, Option<vulkan_abstraction::BufferUpload>)> {
+        let usage_flags = vk::BufferUsageFlags::TRANSFER_DST
+            | vk::BufferUsageFlags::VERTEX_BUFFER
+            | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS
+            | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR;
+
+        let (buffer, upload) =
+            vulkan_abstraction::GpuOnlyBuffer::new_with_upload(core, data, usage_flags, "vertex buffer for BLAS usage")?;
+        let vertex_buffer = Self {
+            buffer,
+            len: data.len(),
+            stride: std::mem::size_of::<T>(),
+        };
+
+        Ok((vertex_buffer, upload))
+    }
+
     //build a vertex buffer with flags for usage in a blas
     pub fn new_for_blas<T>(core: Rc<vulkan_abstraction::Core>, len: vk::DeviceSize) -> SrResult<Self> {
         let usage_flags = vk::BufferUsageFlags::TRANSFER_DST
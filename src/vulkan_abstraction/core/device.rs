@@ -24,6 +24,7 @@ pub struct Device {
     transfer_queue_family_index: Option<u32>,
     async_compute_queue_family_index: Option<u32>,
     surface_support_details: Option<RefCell<SurfaceSupportDetails>>,
+    texture_compression_bc: bool,
 }
 
 impl Device {
@@ -110,6 +111,10 @@ impl Device {
             )
             .ok_or(SrError::new_custom("No suitable GPU found!".to_string()))?;
 
+        // BC texture formats are optional: KTX2 textures fall back to RGBA8 without them
+        let texture_compression_bc =
+            unsafe { instance.get_physical_device_features(physical_device) }.texture_compression_bc == vk::TRUE;
+
         let device = {
             let graphics_priorities = [1.0];
             let transfer_priorities = [0.5];
@@ -189,6 +194,7 @@ impl Device {
             let mut physical_device_features = vk::PhysicalDeviceFeatures2::default().features(
                 vk::PhysicalDeviceFeatures::default()
                     .sampler_anisotropy(true)
+                    .texture_compression_bc(texture_compression_bc)
                     .shader_storage_image_read_without_format(true)
                     .shader_storage_image_write_without_format(true)
                     // r11f_g11f_b10f / rg16f are storage formats from the extended set;
@@ -274,6 +280,7 @@ impl Device {
             transfer_queue_family_index,
             async_compute_queue_family_index,
             surface_support_details,
+            texture_compression_bc,
         })
     }
 
@@ -365,6 +372,11 @@ impl Device {
         &self.physical_device_descriptor_heap_properties
     }
 
+    /// Whether the BC1-7 block-compressed formats can be sampled.
+    pub fn supports_texture_compression_bc(&self) -> bool {
+        self.texture_compression_bc
+    }
+
     pub fn memory_properties(&self) -> &vk::PhysicalDeviceMemoryProperties {
         &self.physical_device_memory_properties
     }
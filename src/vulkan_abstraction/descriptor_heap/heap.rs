@@ -293,6 +293,15 @@ impl DescriptorHeap {
         self.resource.base_index(section)
     }
 
+    /// How many slots the section of `section` has room for.
+    pub fn resource_section_capacity(&self, section: ResourceSection) -> u32 {
+        match section {
+            ResourceSection::Image => self.resource.image_alloc.capacity(),
+            ResourceSection::TexelBuffer => self.resource.texel_alloc.capacity(),
+            ResourceSection::Buffer => self.resource.buffer_alloc.capacity(),
+        }
+    }
+
     /// Write an image descriptor into the resource heap at `slot`. The view-create-info
     /// is used by the driver to construct the on-heap descriptor; it does not need to
     /// outlive this call.
@@ -111,4 +111,8 @@ impl SlotAllocator {
         debug_assert!(index < self.high_water);
         self.free_list.push(index);
     }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
 }
@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use ash::vk;
+use nalgebra as na;
+
+use crate::vulkan_abstraction::gltf::{Node, Trs};
+
+/// What an animation channel drives. Morph target weights are not imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnimatedProperty {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+/// One channel of a glTF animation: a sampler driving one property of one
+/// node.
+#[derive(Clone, Debug)]
+pub struct AnimationChannel {
+    /// Index in the glTF document of the animated node.
+    pub node: usize,
+    pub property: AnimatedProperty,
+    pub interpolation: gltf::animation::Interpolation,
+    /// Keyframe times in seconds, increasing.
+    pub times: Vec<f32>,
+    /// The keyframe values: vectors padded with a zero `w`, rotations as
+    /// `(x, y, z, w)` quaternions. Cubic spline samplers store three values per
+    /// keyframe: in-tangent, value, out-tangent.
+    pub values: Vec<na::Vector4<f32>>,
+}
+
+impl AnimationChannel {
+    /// The channel's value at `time`, clamped to the keyframe range. `None`
+    /// for a channel without keyframes.
+    pub fn sample(&self, time: f32) -> Option<na::Vector4<f32>> {
+        let last = self.times.len().checked_sub(1)?;
+        let cubic = self.interpolation == gltf::animation::Interpolation::CubicSpline;
+        let value = |k: usize| if cubic { self.values[3 * k + 1] } else { self.values[k] };
+
+        // the keyframe at or before `time`
+        let k = self.times.partition_point(|&t| t <= time).saturating_sub(1);
+        if time <= self.times[0] || k == last {
+            return Some(value(k));
+        }
+
+        let (t0, t1) = (self.times[k], self.times[k + 1]);
+        let delta = t1 - t0;
+        let factor = (time - t0) / delta;
+
+        let sampled = match self.interpolation {
+            gltf::animation::Interpolation::Step => value(k),
+            gltf::animation::Interpolation::Linear => {
+                if self.property == AnimatedProperty::Rotation {
+                    let q0 = to_unit_quaternion(value(k));
+                    let q1 = to_unit_quaternion(value(k + 1));
+                    // slerp is ill-defined between opposite rotations: blend linearly there
+                    let q = q0
+                        .try_slerp(&q1, factor, f32::EPSILON)
+                        .unwrap_or_else(|| q0.nlerp(&q1, factor));
+                    na::Vector4::new(q.i, q.j, q.k, q.w)
+                } else {
+                    value(k).lerp(&value(k + 1), factor)
+                }
+            }
+            gltf::animation::Interpolation::CubicSpline => {
+                // the Hermite spline of the glTF spec, with the tangents scaled by the keyframe delta
+                let (p0, m0) = (value(k), self.values[3 * k + 2] * delta);
+                let (p1, m1) = (value(k + 1), self.values[3 * (k + 1)] * delta);
+                let (t, t2, t3) = (factor, factor * factor, factor * factor * factor);
+
+                p0 * (2.0 * t3 - 3.0 * t2 + 1.0) + m0 * (t3 - 2.0 * t2 + t) + p1 * (-2.0 * t3 + 3.0 * t2) + m1 * (t3 - t2)
+            }
+        };
+
+        Some(sampled)
+    }
+}
+
+/// A glTF animation clip. Sampling it at a time re-poses the nodes it
+/// animates; [`Self::instances`] turns that into the instance list
+/// `Renderer::render` takes.
+///
+/// Only the instances follow the animation: cameras and lights stay where the
+/// scene placed them.
+#[derive(Clone, Debug)]
+pub struct SceneAnimation {
+    pub name: Option<String>,
+    pub channels: Vec<AnimationChannel>,
+}
+
+impl SceneAnimation {
+    /// The time of the clip's last keyframe, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.channels
+            .iter()
+            .filter_map(|channel| channel.times.last().copied())
+            .fold(0.0, f32::max)
+    }
+
+    /// The local transform of every node the clip animates at `time`, starting
+    /// from the node's authored transform for the properties it leaves alone.
+    pub fn local_transforms(&self, scene: &crate::Scene, time: f32) -> HashMap<usize, na::Matrix4<f32>> {
+        let mut rest_trs = HashMap::new();
+        for node in scene.nodes() {
+            collect_local_trs(node, &mut rest_trs);
+        }
+
+        let mut animated: HashMap<usize, Trs> = HashMap::new();
+        for channel in &self.channels {
+            let Some(rest) = rest_trs.get(&channel.node) else {
+                continue; // the node belongs to another scene of the document
+            };
+            let Some(value) = channel.sample(time) else {
+                continue;
+            };
+
+            let trs = animated.entry(channel.node).or_insert(*rest);
+            match channel.property {
+                AnimatedProperty::Translation => trs.translation = value.xyz(),
+                AnimatedProperty::Rotation => trs.rotation = to_unit_quaternion(value),
+                AnimatedProperty::Scale => trs.scale = value.xyz(),
+            }
+        }
+
+        animated.into_iter().map(|(node, trs)| (node, trs.to_matrix())).collect()
+    }
+
+    /// The world transform of every node of `scene` at `time`.
+    pub fn world_transforms(&self, scene: &crate::Scene, time: f32) -> HashMap<usize, na::Matrix4<f32>> {
+        let local_transforms = self.local_transforms(scene, time);
+
+        scene.world_transforms(|node| {
+            local_transforms
+                .get(&node.index())
+                .copied()
+                .unwrap_or(*node.local_transform())
+        })
+    }
+
+    /// The instances of a scene loaded with `Renderer::load_scene`, posed at
+    /// `time`: `load.instances` with every transform replaced by its node's
+    /// animated world transform.
+    pub fn instances<K: Copy>(
+        &self,
+        scene: &crate::Scene,
+        load: &crate::SceneLoad<K>,
+        time: f32,
+    ) -> Vec<(K, Vec<vk::TransformMatrixKHR>)> {
+        load.posed_instances(&self.world_transforms(scene, time))
+    }
+
+    /// The joint matrices of every skinned instance of `load` at `time`, ready
+    /// for `Renderer::set_joint_matrices`.
+    pub fn joint_matrices<K: Copy>(
+        &self,
+        scene: &crate::Scene,
+        load: &crate::SceneLoad<K>,
+        time: f32,
+    ) -> Vec<(K, Vec<na::Matrix4<f32>>)> {
+        load.posed_joint_matrices(&self.world_transforms(scene, time))
+    }
+}
+
+fn collect_local_trs(node: &Node, local_trs: &mut HashMap<usize, Trs>) {
+    local_trs.insert(node.index(), *node.local_trs());
+
+    if let Some(children) = node.children() {
+        for child in children {
+            collect_local_trs(child, local_trs);
+        }
+    }
+}
+
+fn to_unit_quaternion(xyzw: na::Vector4<f32>) -> na::UnitQuaternion<f32> {
+    na::UnitQuaternion::new_normalize(na::Quaternion::new(xyzw.w, xyzw.x, xyzw.y, xyzw.z))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The clip slides the root along x (linear) and steps the scale of its
+    /// child, which carries the mesh: the instance follows both.
+    #[test]
+    fn animation_reposes_the_instances() {
+        let (scene, _) = crate::vulkan_abstraction::gltf::load_test_scene("animated.gltf");
+        let load = crate::SceneLoad::for_test(&scene);
+        assert_eq!(load.instance_nodes, vec![vec![1]]);
+
+        let animation = &scene.animations()[0];
+        assert_eq!(animation.name.as_deref(), Some("slide"));
+        assert_eq!(animation.duration(), 2.0);
+
+        // (time, x translation, scale)
+        for (time, x, scale) in [(0.5, 1.0, 1.0), (1.5, 3.0, 2.0), (5.0, 4.0, 2.0)] {
+            let instances = animation.instances(&scene, &load, time);
+            let transform = &instances[0].1[0];
+            assert!((transform.matrix[3] - x).abs() < 1e-6, "x at {time}: {}", transform.matrix[3]);
+            assert_eq!(transform.matrix[7], 1.0, "the child's own translation is kept");
+            assert_eq!(transform.matrix[0], scale, "scale at {time}");
+        }
+    }
+
+    #[test]
+    fn channels_interpolate_as_the_spec_says() {
+        // cubic spline with flat tangents: halfway through, halfway between the keyframes
+        let cubic = AnimationChannel {
+            node: 0,
+            property: AnimatedProperty::Scale,
+            interpolation: gltf::animation::Interpolation::CubicSpline,
+            times: vec![0.0, 2.0],
+            values: [[0.0; 4], [1.0; 4], [0.0; 4], [0.0; 4], [3.0; 4], [0.0; 4]]
+                .map(na::Vector4::from)
+                .to_vec(),
+        };
+        assert_eq!(cubic.sample(1.0), Some(na::Vector4::repeat(2.0)));
+        assert_eq!(cubic.sample(-1.0), Some(na::Vector4::repeat(1.0)));
+
+        // rotations are slerped: halfway through a quarter turn is an eighth of one
+        let quarter_turn = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+        let rotation = AnimationChannel {
+            node: 0,
+            property: AnimatedProperty::Rotation,
+            interpolation: gltf::animation::Interpolation::Linear,
+            times: vec![0.0, 1.0],
+            values: vec![
+                na::Vector4::new(0.0, 0.0, 0.0, 1.0),
+                na::Vector4::new(quarter_turn.i, quarter_turn.j, quarter_turn.k, quarter_turn.w),
+            ],
+        };
+        let halfway = rotation.sample(0.5).unwrap();
+        let eighth_turn = na::UnitQuaternion::from_axis_angle(&na::Vector3::y_axis(), std::f32::consts::FRAC_PI_4);
+        assert!(
+            (halfway - na::Vector4::new(eighth_turn.i, eighth_turn.j, eighth_turn.k, eighth_turn.w))
+                .abs()
+                .max()
+                < 1e-6
+        );
+    }
+}
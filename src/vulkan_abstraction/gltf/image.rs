@@ -1,6 +1,294 @@
+use ash::vk;
+
+use crate::error::{SrError, SrResult};
+use crate::vulkan_abstraction::gltf::TexelEncoding;
+
+#[derive(Clone)]
 pub struct Image {
-    pub format: gltf::image::Format,
     pub height: usize,
     pub width: usize,
-    pub raw_data: Vec<u8>,
+    pub pixels: ImagePixels,
+    /// How the slots sampling the image read it, which picks between the
+    /// `_SRGB` and `_UNORM` variant of its format on upload.
+    pub encoding: TexelEncoding,
+}
+
+/// The texels of an [`Image`], as the document stores them.
+#[derive(Clone)]
+pub enum ImagePixels {
+    /// The base level of a PNG or JPEG image, decoded. Its mip chain is
+    /// generated by [`crate::SceneData::prepare_images`].
+    Decoded { format: gltf::image::Format, raw_data: Vec<u8> },
+    /// The mip levels of a KTX2 image in a format the GPU samples directly
+    /// (a BC block format, as a rule), largest first.
+    Levels { format: vk::Format, levels: Vec<Vec<u8>> },
+    /// The mip levels of a UASTC KTX2 image, largest first, transcoded by
+    /// [`crate::SceneData::prepare_images`] to whatever the device samples:
+    /// see [`Image::transcode_uastc`].
+    Uastc { levels: Vec<Vec<u8>> },
+}
+
+/// The bytes every KTX2 file starts with.
+const KTX2_IDENTIFIER: [u8; 12] = [0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'];
+
+impl Image {
+    /// The size of the texels as stored, all mip levels included.
+    pub fn byte_size(&self) -> usize {
+        match &self.pixels {
+            ImagePixels::Decoded { raw_data, .. } => raw_data.len(),
+            ImagePixels::Levels { levels, .. } | ImagePixels::Uastc { levels } => levels.iter().map(Vec::len).sum(),
+        }
+    }
+
+    pub fn is_ktx2(bytes: &[u8]) -> bool {
+        bytes.starts_with(&KTX2_IDENTIFIER)
+    }
+
+    /// Read a 2D KTX2 image (`KHR_texture_basisu`), undoing its Zstandard
+    /// supercompression. ETC1S (BasisLZ) images are not supported: they have
+    /// to be re-encoded as UASTC.
+    pub fn from_ktx2(bytes: &[u8], name: &str) -> SrResult<Self> {
+        let error = |message: String| SrError::new_custom(format!("gltf: KTX2 image {name:?}: {message}"));
+
+        let reader = ktx2::Reader::new(bytes).map_err(|e| error(e.to_string()))?;
+        let header = reader.header();
+
+        if header.pixel_depth > 1 || header.layer_count > 1 || header.face_count > 1 {
+            return Err(error(
+                "only 2D textures are supported, not volumes, arrays or cubemaps".to_string(),
+            ));
+        }
+
+        let levels = reader
+            .levels()
+            .map(|level| match header.supercompression_scheme {
+                None => Ok(level.data.to_vec()),
+                Some(ktx2::SupercompressionScheme::Zstandard) => {
+                    let mut decoder = ruzstd::decoding::StreamingDecoder::new(level.data).map_err(|e| error(e.to_string()))?;
+                    let mut decompressed = Vec::new();
+                    std::io::Read::read_to_end(&mut decoder, &mut decompressed).map_err(|e| error(e.to_string()))?;
+                    Ok(decompressed)
+                }
+                Some(scheme) => Err(error(format!("unsupported supercompression {scheme:?}"))),
+            })
+            .collect::<SrResult<Vec<_>>>()?;
+
+        let pixels = match header.format {
+            // KTX2 stores Vulkan format numbers
+            Some(format) => ImagePixels::Levels {
+                format: vk::Format::from_raw(format.0.get() as i32),
+                levels,
+            },
+            // without a format, the data is Basis Universal: ETC1S is always BasisLZ-supercompressed, UASTC never is
+            None if header.supercompression_scheme == Some(ktx2::SupercompressionScheme::BasisLZ) => {
+                return Err(error("ETC1S textures are not supported, encode it as UASTC".to_string()));
+            }
+            None => ImagePixels::Uastc { levels },
+        };
+
+        Ok(Self {
+            height: header.pixel_height.max(1) as usize,
+            width: header.pixel_width as usize,
+            pixels,
+            encoding: TexelEncoding::Linear,
+        })
+    }
+
+    /// Transcode the levels of a `width`x`height` UASTC image to BC7, or to
+    /// RGBA8 when `bc7_supported` is false, returning the format they end up in.
+    pub fn transcode_uastc(
+        levels: &[Vec<u8>],
+        width: usize,
+        height: usize,
+        bc7_supported: bool,
+    ) -> SrResult<(vk::Format, Vec<Vec<u8>>)> {
+        use basis_universal::{DecodeFlags, LowLevelUastcTranscoder, SliceParametersUastc, TranscoderBlockFormat};
+
+        let (block_format, format) = if bc7_supported {
+            (TranscoderBlockFormat::BC7, vk::Format::BC7_UNORM_BLOCK)
+        } else {
+            (TranscoderBlockFormat::RGBA32, vk::Format::R8G8B8A8_UNORM)
+        };
+
+        basis_universal::transcoder_init();
+        let transcoder = LowLevelUastcTranscoder::new();
+
+        let levels = levels
+            .iter()
+            .enumerate()
+            .map(|(level, data)| {
+                let width = (width as u32 >> level).max(1);
+                let height = (height as u32 >> level).max(1);
+
+                // UASTC blocks are 4x4 texels, 16 bytes each
+                transcoder
+                    .transcode_slice(
+                        data,
+                        SliceParametersUastc {
+                            num_blocks_x: width.div_ceil(4),
+                            num_blocks_y: height.div_ceil(4),
+                            has_alpha: false,
+                            original_width: width,
+                            original_height: height,
+                        },
+                        DecodeFlags::HIGH_QUALITY,
+                        block_format,
+                    )
+                    .map_err(|e| {
+                        SrError::new_custom(format!("gltf: cannot transcode UASTC mip level {level} to {format:?}: {e:?}"))
+                    })
+            })
+            .collect::<SrResult<Vec<_>>>()?;
+
+        Ok((format, levels))
+    }
+}
+
+/// Rewrite the texels of a decoded `format` image as tightly packed `target`
+/// texels. Gray images spread their gray over the color channels, alpha
+/// channels `target` adds are opaque, and 8 and 16-bit channels are rescaled
+/// to each other. `None` if `target` is not an uncompressed three or four
+/// channel format of the same kind (normalized or float) as `format`.
+pub fn convert_decoded(format: gltf::image::Format, raw_data: &[u8], target: vk::Format) -> Option<Vec<u8>> {
+    use gltf::image::Format;
+
+    let (channels, channel_size) = match format {
+        Format::R8 => (1, 1),
+        Format::R8G8 => (2, 1),
+        Format::R8G8B8 => (3, 1),
+        Format::R8G8B8A8 => (4, 1),
+        Format::R16 => (1, 2),
+        Format::R16G16 => (2, 2),
+        Format::R16G16B16 => (3, 2),
+        Format::R16G16B16A16 => (4, 2),
+        Format::R32G32B32FLOAT => (3, 4),
+        Format::R32G32B32A32FLOAT => (4, 4),
+    };
+    let (target_channels, target_channel_size) = match target {
+        vk::Format::R8G8B8_UNORM | vk::Format::R8G8B8_SRGB => (3, 1),
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => (4, 1),
+        vk::Format::R16G16B16_UNORM => (3, 2),
+        vk::Format::R16G16B16A16_UNORM => (4, 2),
+        vk::Format::R32G32B32_SFLOAT => (3, 4),
+        vk::Format::R32G32B32A32_SFLOAT => (4, 4),
+        _ => return None,
+    };
+    if (channel_size == 4) != (target_channel_size == 4) {
+        return None;
+    }
+
+    // the channel of the source texel each target channel is read from, `None` for an opaque alpha
+    let sources = match channels {
+        1 => [Some(0), Some(0), Some(0), None],
+        2 => [Some(0), Some(0), Some(0), Some(1)],
+        3 => [Some(0), Some(1), Some(2), None],
+        _ => [Some(0), Some(1), Some(2), Some(3)],
+    };
+    let opaque = match target_channel_size {
+        4 => 1.0f32.to_ne_bytes().to_vec(),
+        size => vec![0xFF; size],
+    };
+
+    let texel_size = channels * channel_size;
+    let mut converted = Vec::with_capacity(raw_data.len() / texel_size * target_channels * target_channel_size);
+    for texel in raw_data.chunks_exact(texel_size) {
+        for source in &sources[..target_channels] {
+            let Some(source) = source else {
+                converted.extend_from_slice(&opaque);
+                continue;
+            };
+            let value = &texel[source * channel_size..(source + 1) * channel_size];
+            match (channel_size, target_channel_size) {
+                (2, 1) => {
+                    let value = u16::from_ne_bytes([value[0], value[1]]) as u32;
+                    converted.push(((value * 255 + 32767) / 65535) as u8);
+                }
+                (1, 2) => converted.extend_from_slice(&(value[0] as u16 * 257).to_ne_bytes()),
+                _ => converted.extend_from_slice(value),
+            }
+        }
+    }
+
+    Some(converted)
+}
+
+/// The mip chain of a `width`x`height` image of tightly packed `format`
+/// texels, from `base` down to 1x1: each level averages 2x2 texels of the one
+/// above it, sRGB colors in linear space. Just `base` if `format` is not one
+/// [`convert_decoded`] converts to.
+pub fn mip_chain(base: Vec<u8>, width: usize, height: usize, format: vk::Format) -> Vec<Vec<u8>> {
+    let (channels, channel_size) = match format {
+        vk::Format::R8G8B8_UNORM | vk::Format::R8G8B8_SRGB => (3, 1),
+        vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB => (4, 1),
+        vk::Format::R16G16B16_UNORM => (3, 2),
+        vk::Format::R16G16B16A16_UNORM => (4, 2),
+        vk::Format::R32G32B32_SFLOAT => (3, 4),
+        vk::Format::R32G32B32A32_SFLOAT => (4, 4),
+        _ => return vec![base],
+    };
+    // alpha is linear in sRGB formats too
+    let srgb = matches!(format, vk::Format::R8G8B8_SRGB | vk::Format::R8G8B8A8_SRGB);
+    let is_srgb_channel = |channel: usize| srgb && channel < 3;
+
+    let read = |texel: &[u8], channel: usize| -> f32 {
+        let value = &texel[channel * channel_size..(channel + 1) * channel_size];
+        match channel_size {
+            1 if is_srgb_channel(channel) => srgb_to_linear(value[0] as f32 / 255.0),
+            1 => value[0] as f32 / 255.0,
+            2 => u16::from_ne_bytes([value[0], value[1]]) as f32 / 65535.0,
+            _ => f32::from_ne_bytes([value[0], value[1], value[2], value[3]]),
+        }
+    };
+    let write = |level: &mut Vec<u8>, channel: usize, value: f32| match channel_size {
+        1 if is_srgb_channel(channel) => level.push((linear_to_srgb(value).clamp(0.0, 1.0) * 255.0 + 0.5) as u8),
+        1 => level.push((value.clamp(0.0, 1.0) * 255.0 + 0.5) as u8),
+        2 => level.extend_from_slice(&((value.clamp(0.0, 1.0) * 65535.0 + 0.5) as u16).to_ne_bytes()),
+        _ => level.extend_from_slice(&value.to_ne_bytes()),
+    };
+
+    let texel_size = channels * channel_size;
+    let (mut width, mut height) = (width.max(1), height.max(1));
+    let mut levels = vec![base];
+    while width > 1 || height > 1 {
+        let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+        let above = &levels[levels.len() - 1];
+        let mut level = Vec::with_capacity(next_width * next_height * texel_size);
+
+        for y in 0..next_height {
+            // a side one texel long is averaged with itself
+            let rows = [2 * y, (2 * y + 1).min(height - 1)];
+            for x in 0..next_width {
+                let columns = [2 * x, (2 * x + 1).min(width - 1)];
+                for channel in 0..channels {
+                    let sum: f32 = rows
+                        .iter()
+                        .flat_map(|&row| columns.iter().map(move |&column| (row * width + column) * texel_size))
+                        .map(|offset| read(&above[offset..offset + texel_size], channel))
+                        .sum();
+                    write(&mut level, channel, sum / 4.0);
+                }
+            }
+        }
+
+        levels.push(level);
+        (width, height) = (next_width, next_height);
+    }
+
+    levels
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
 }
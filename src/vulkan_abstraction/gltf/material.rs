@@ -1,23 +1,74 @@
+use crate::vulkan_abstraction::gltf::{TexelEncoding, TextureTransform};
+
 #[derive(Clone)]
 pub struct PbrMetallicRoughnessProperties {
     pub base_color_factor: [f32; 4],
     pub metallic_factor: f32,
     pub roughness_factor: f32,
     pub base_color_texture_index: Option<usize>,
+    pub base_color_texture_transform: TextureTransform,
     pub metallic_roughness_texture_index: Option<usize>,
+    pub metallic_roughness_texture_transform: TextureTransform,
 }
 
 #[derive(Clone)]
 pub struct Material {
     pub pbr_metallic_roughness_properties: PbrMetallicRoughnessProperties,
     pub normal_texture_index: Option<usize>,
+    pub normal_texture_transform: TextureTransform,
     pub occlusion_texture_index: Option<usize>,
+    pub occlusion_texture_transform: TextureTransform,
+    /// How much of the occlusion texture applies: 0 ignores it, 1 (the
+    /// default) uses it as is.
+    pub occlusion_strength: f32,
     pub emissive_factor: [f32; 3],
     pub emissive_strength: f32,
     pub emissive_texture_index: Option<usize>,
+    pub emissive_texture_transform: TextureTransform,
     pub alpha_mode: gltf::material::AlphaMode,
     pub alpha_cutoff: f32,
     pub double_sided: bool,
     pub transmission_factor: f32,
     pub ior: f32,
+    /// KHR_materials_volume. A zero thickness is a thin-walled surface, with
+    /// nothing inside to absorb light.
+    pub thickness_factor: f32,
+    /// Distance light travels in the medium before reaching `attenuation_color`,
+    /// infinite for a clear medium.
+    pub attenuation_distance: f32,
+    pub attenuation_color: [f32; 3],
+    /// KHR_materials_clearcoat. The clearcoat normal texture is sampled with
+    /// the normal texture's set of coordinates.
+    pub clearcoat_factor: f32,
+    pub clearcoat_roughness_factor: f32,
+    pub clearcoat_normal_texture_index: Option<usize>,
+    pub clearcoat_normal_texture_transform: TextureTransform,
+    /// KHR_materials_sheen
+    pub sheen_color_factor: [f32; 3],
+    pub sheen_roughness_factor: f32,
+    /// KHR_materials_specular
+    pub specular_factor: f32,
+    pub specular_color_factor: [f32; 3],
+}
+
+impl Material {
+    /// Whether the surface hides everything behind it, alpha aside: `MASK` and
+    /// `BLEND` materials need the any-hit shader.
+    pub fn is_opaque(&self) -> bool {
+        self.alpha_mode == gltf::material::AlphaMode::Opaque
+    }
+
+    /// The texture index of every texture slot, with the encoding the slot
+    /// reads its texels in.
+    pub fn texture_indices(&self) -> [(Option<usize>, TexelEncoding); 6] {
+        let pbr = &self.pbr_metallic_roughness_properties;
+        [
+            (pbr.base_color_texture_index, TexelEncoding::Srgb),
+            (pbr.metallic_roughness_texture_index, TexelEncoding::Linear),
+            (self.normal_texture_index, TexelEncoding::Linear),
+            (self.occlusion_texture_index, TexelEncoding::Linear),
+            (self.emissive_texture_index, TexelEncoding::Srgb),
+            (self.clearcoat_normal_texture_index, TexelEncoding::Linear),
+        ]
+    }
 }
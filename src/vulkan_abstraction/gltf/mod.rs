@@ -1,4 +1,4 @@
-use std::{collections::HashMap, rc::Rc};
+use std::collections::{HashMap, HashSet};
 
 use crate::{
     error::{SrError, SrResult},
@@ -7,73 +7,221 @@ use crate::{
 
 use nalgebra as na;
 
+pub mod animation;
 pub mod emissive_triangle;
 pub mod image;
 pub mod material;
 pub mod mesh;
+pub mod morph_target;
 pub mod node;
 pub mod primitive;
+pub mod punctual_light;
+pub mod scene_info;
+pub mod skin;
+pub mod tangent_space;
 pub mod texture;
+pub mod uri_resolver;
 pub mod vertex;
 
+pub use animation::*;
 pub use emissive_triangle::*;
 pub use image::*;
 pub use material::*;
 pub use mesh::*;
+pub use morph_target::*;
 pub use node::*;
 pub use primitive::*;
+pub use punctual_light::*;
+pub use scene_info::*;
+pub use skin::*;
+pub use tangent_space::*;
 pub use texture::*;
+pub use uri_resolver::*;
 pub use vertex::*;
 
 macro_rules! get_texture_indices {
     ($material:ident, $texture_name:ident) => {
         match $material.$texture_name() {
-            Some(texture_info) => (Some(texture_info.texture().index()), texture_info.tex_coord()),
-            None => (None, 0),
+            Some(texture_info) => {
+                let transform = texture_info.texture_transform();
+                // KHR_texture_transform may also pick another set of coordinates
+                let tex_coord = transform.as_ref().and_then(|transform| transform.tex_coord());
+                (
+                    Some(texture_info.texture().index()),
+                    tex_coord.unwrap_or(texture_info.tex_coord()),
+                    transform.map(TextureTransform::from).unwrap_or_default(),
+                )
+            }
+            None => (None, 0, TextureTransform::default()),
+        }
+    };
+    // gltf only parses KHR_texture_transform on plain texture infos: the normal and
+    // occlusion textures carry it as raw JSON
+    ($material:ident, $texture_name:ident, json) => {
+        match $material.$texture_name() {
+            Some(texture_info) => {
+                let (transform, tex_coord) = texture_info
+                    .extension_value("KHR_texture_transform")
+                    .map(texture_transform_from_json)
+                    .unwrap_or_default();
+                (
+                    Some(texture_info.texture().index()),
+                    tex_coord.unwrap_or(texture_info.tex_coord()),
+                    transform,
+                )
+            }
+            None => (None, 0, TextureTransform::default()),
         }
     };
 }
 
 macro_rules! insert_tex_coords {
     ($reader:ident, $vertices:ident, $tex_coord_index:expr, $texture_name_coord:ident) => {
-        $reader
-            .read_tex_coords($tex_coord_index)
-            .unwrap()
-            .into_f32()
-            .enumerate()
-            .for_each(|(j, coord)| $vertices[j].$texture_name_coord = coord);
+        // a missing set (or no texture at all, which defaults to set 0) leaves the coordinates at zero
+        if let Some(tex_coords) = $reader.read_tex_coords($tex_coord_index) {
+            tex_coords
+                .into_f32()
+                .enumerate()
+                .for_each(|(j, coord)| $vertices[j].$texture_name_coord = coord);
+        }
     };
 }
 
 pub type PrimitiveDataMap = HashMap<vulkan_abstraction::gltf::PrimitiveUniqueKey, vulkan_abstraction::gltf::PrimitiveData>;
 
 pub struct Gltf {
-    core: Rc<vulkan_abstraction::Core>,
     document: gltf::Document,
     buffers: Vec<gltf::buffer::Data>,
-    images: Vec<gltf::image::Data>,
+    /// By image index. Images no texture samples are not decoded (`None`):
+    /// the PNG/JPEG fallbacks of `KHR_texture_basisu` textures, mostly.
+    images: Vec<Option<Image>>,
+    /// How the primitives missing normals get theirs.
+    normal_generation: NormalGeneration,
 }
 
 impl Gltf {
-    pub fn new(core: Rc<vulkan_abstraction::Core>, path: &str) -> SrResult<Self> {
-        let (document, buffers, images) = gltf::import(path)?;
+    pub fn new(path: &str) -> SrResult<Self> {
+        let bytes = std::fs::read(path).map_err(|e| SrError::new_custom(format!("gltf: cannot read {path:?}: {e}")))?;
+        let directory = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new(""));
+
+        Self::from_slice(&bytes, &Directory(directory.to_path_buf()))
+    }
+
+    /// Parse a glTF document held in memory: a .glb, or a .gltf whose
+    /// external buffers and images `resolver` supplies. Embedded data is read
+    /// directly, so a self-contained document only needs [`EmbeddedOnly`].
+    pub fn from_slice(bytes: &[u8], resolver: &dyn UriResolver) -> SrResult<Self> {
+        let gltf::Gltf { document, mut blob } = gltf::Gltf::from_slice(bytes)?;
+
+        let mut buffers = vec![];
+        for buffer in document.buffers() {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => blob
+                    .take()
+                    .ok_or_else(|| SrError::new_custom("gltf: the binary chunk of the document is missing".to_string()))?,
+                gltf::buffer::Source::Uri(uri) if !is_data_uri(uri) => resolver.resolve(uri)?,
+                source => gltf::buffer::Data::from_source(source, None)?.0,
+            };
+
+            if data.len() < buffer.length() {
+                return Err(SrError::new_custom(format!(
+                    "gltf: buffer {} is {} bytes long, but only {} were found",
+                    buffer.index(),
+                    buffer.length(),
+                    data.len()
+                )));
+            }
+            buffers.push(gltf::buffer::Data(data));
+        }
+
+        let image_count = document.images().len();
+        let sampled_images: HashSet<usize> = document
+            .textures()
+            .map(|texture| texture_source(&texture, image_count))
+            .collect();
+
+        let mut encoded_images = vec![];
+        for image in document.images() {
+            if !sampled_images.contains(&image.index()) {
+                continue;
+            }
+
+            let name = image.name().map_or_else(|| format!("#{}", image.index()), String::from);
+            let bytes = match image.source() {
+                gltf::image::Source::Uri { uri, .. } if !is_data_uri(uri) => resolver.resolve(uri)?,
+                gltf::image::Source::Uri { uri, .. } => gltf::buffer::Data::from_source(gltf::buffer::Source::Uri(uri), None)?.0,
+                gltf::image::Source::View { view, .. } => {
+                    buffers[view.buffer().index()][view.offset()..view.offset() + view.length()].to_vec()
+                }
+            };
+            encoded_images.push((image.index(), name, bytes));
+        }
+
+        let mut images: Vec<Option<Image>> = (0..image_count).map(|_| None).collect();
+        for (index, image) in decode_images(&encoded_images)? {
+            images[index] = Some(image);
+        }
 
         Ok(Self {
-            core,
             document,
             buffers,
             images,
+            normal_generation: NormalGeneration::default(),
         })
     }
 
+    /// Generate the missing normals of the scenes created from now on as
+    /// `normal_generation` asks, flat by default.
+    pub fn set_normal_generation(&mut self, normal_generation: NormalGeneration) {
+        self.normal_generation = normal_generation;
+    }
+
     pub fn create_default_scene(&self) -> SrResult<(crate::Scene, crate::SceneData)> {
+        self.create_scene(self.default_scene_index())
+    }
+
+    fn default_scene_index(&self) -> usize {
         // find the defualt scene index
-        let default_scene_index = match self.document.default_scene() {
+        match self.document.default_scene() {
             Some(s) => s.index(),
             None => 0,
+        }
+    }
+
+    /// The scenes of the document, in document order.
+    pub fn scenes(&self) -> Vec<SceneInfo> {
+        fn count_nodes(node: gltf::Node) -> usize {
+            1 + node.children().map(count_nodes).sum::<usize>()
+        }
+
+        let default_scene_index = self.default_scene_index();
+
+        self.document
+            .scenes()
+            .map(|scene| SceneInfo {
+                index: scene.index(),
+                name: scene.name().map(String::from),
+                node_count: scene.nodes().map(count_nodes).sum(),
+                is_default: scene.index() == default_scene_index,
+            })
+            .collect()
+    }
+
+    /// The index of the scene `selector` picks, if the document has it.
+    pub fn scene_index(&self, selector: &SceneSelector) -> SrResult<usize> {
+        let index = match selector {
+            SceneSelector::Default => Some(self.default_scene_index()),
+            SceneSelector::Index(index) => Some(*index),
+            SceneSelector::Name(name) => self
+                .document
+                .scenes()
+                .find(|scene| scene.name() == Some(name.as_str()))
+                .map(|scene| scene.index()),
         };
 
-        self.create_scene(default_scene_index)
+        index
+            .filter(|&index| index < self.document.scenes().len())
+            .ok_or_else(|| SrError::new_custom(format!("gltf: No scene {selector:?} found")))
     }
 
     pub fn create_scene(&self, scene_index: usize) -> SrResult<(crate::Scene, crate::SceneData)> {
@@ -98,41 +246,67 @@ impl Gltf {
             })
             .collect::<Vec<_>>();
 
-        let textures = self
-            .document
-            .textures()
-            .map(|texture| Texture {
-                sampler: texture.sampler().index(),
-                source: texture.source().index(),
-            })
-            .collect::<Vec<_>>();
-
-        let images = self
-            .images
-            .iter()
-            .map(|image| Image {
-                format: image.format,
-                height: image.height as usize,
-                width: image.width as usize,
-                raw_data: image.pixels.clone(), // TODO: consume gltf
-            })
-            .collect::<Vec<_>>();
-
         let mut nodes: Vec<Node> = vec![];
         let mut primitive_data_map: PrimitiveDataMap = PrimitiveDataMap::new();
+        let mut skipped_primitives = vec![];
         for gltf_node in gltf_scene.nodes() {
             // the root nodes do not have a parent transform to apply
             let transform = na::Matrix4::identity();
-            let node = self.explore(&gltf_node, transform, &mut primitive_data_map)?;
+            let node = self.explore(&gltf_node, transform, &mut primitive_data_map, &mut skipped_primitives)?;
             nodes.push(node);
         }
 
-        let scene = crate::Scene::new(nodes)?;
+        let mut texture_encodings = HashMap::new();
+        for node in &nodes {
+            collect_texture_encodings(node, &mut texture_encodings);
+        }
+
+        // an image is uploaded once per encoding its textures are sampled with: the
+        // textures index the uploads
+        let mut images = vec![];
+        let mut image_positions = HashMap::new();
+        let mut textures = vec![];
+        for texture in self.document.textures() {
+            let source = texture_source(&texture, self.images.len());
+            // a texture no material of the scene samples is kept linear
+            let encodings = texture_encodings
+                .remove(&texture.index())
+                .unwrap_or_else(|| HashSet::from([TexelEncoding::Linear]));
+
+            let mut image_position = |encoding: TexelEncoding| {
+                encodings.contains(&encoding).then(|| {
+                    *image_positions.entry((source, encoding)).or_insert_with(|| {
+                        let image = self.images[source].as_ref().expect("the images of every texture are decoded");
+                        images.push(Image {
+                            encoding,
+                            ..image.clone() // TODO: consume gltf
+                        });
+                        images.len() - 1
+                    })
+                })
+            };
+
+            textures.push(Texture {
+                sampler: texture.sampler().index(),
+                source: image_position(TexelEncoding::Linear),
+                srgb_source: image_position(TexelEncoding::Srgb),
+            });
+        }
+
+        let skins = self.document.skins().map(|skin| self.process_skin(&skin)).collect();
+        let animations = self
+            .document
+            .animations()
+            .map(|animation| self.process_animation(&animation))
+            .collect();
+
+        let scene = crate::Scene::new(nodes, skins, animations)?;
         let scene_data = crate::SceneData {
             textures,
             images,
             samplers,
             primitive_data_map,
+            skipped_primitives,
         };
 
         Ok((scene, scene_data))
@@ -143,22 +317,124 @@ impl Gltf {
         gltf_node: &gltf::Node,
         parent_transform: na::Matrix4<f32>,
         primitive_data_map: &mut PrimitiveDataMap,
+        skipped_primitives: &mut Vec<SkippedPrimitive>,
     ) -> SrResult<vulkan_abstraction::gltf::Node> {
-        let (transform, mesh) = self.process_node(gltf_node, parent_transform, primitive_data_map)?;
+        let (transform, mesh, camera, light) =
+            self.process_node(gltf_node, parent_transform, primitive_data_map, skipped_primitives)?;
 
         let children = if gltf_node.children().len() == 0 {
             None
         } else {
             let mut children = vec![];
             for gltf_child in gltf_node.children() {
-                let child = self.explore(&gltf_child, transform, primitive_data_map)?;
+                let child = self.explore(&gltf_child, transform, primitive_data_map, skipped_primitives)?;
                 children.push(child);
             }
 
             Some(children)
         };
 
-        vulkan_abstraction::gltf::Node::new(transform, mesh, children)
+        let skin = gltf_node.skin().map(|skin| skin.index());
+
+        // the node's weights override its mesh's
+        let morph_weights = gltf_node
+            .weights()
+            .or_else(|| gltf_node.mesh().and_then(|gltf_mesh| gltf_mesh.weights()))
+            .map(<[f32]>::to_vec)
+            .unwrap_or_default();
+
+        // kept alongside the world transform so animations can re-evaluate the hierarchy
+        let local_transform = na::Matrix4::from(gltf_node.transform().matrix());
+        let local_trs = Trs::from(gltf_node.transform());
+
+        vulkan_abstraction::gltf::Node::new(
+            gltf_node.index(),
+            gltf_node.name().map(String::from),
+            transform,
+            local_transform,
+            local_trs,
+            mesh,
+            camera,
+            light,
+            skin,
+            morph_weights,
+            children,
+        )
+    }
+
+    /// Read a skin's joints and inverse bind matrices. The matrices default to
+    /// identity when the skin doesn't provide them, as the spec says.
+    fn process_skin(&self, gltf_skin: &gltf::Skin) -> Skin {
+        let joints: Vec<usize> = gltf_skin.joints().map(|joint| joint.index()).collect();
+
+        let reader = gltf_skin.reader(|buffer| Some(&self.buffers[buffer.index()]));
+        let inverse_bind_matrices = match reader.read_inverse_bind_matrices() {
+            Some(matrices) => matrices.map(na::Matrix4::from).collect(),
+            None => vec![na::Matrix4::identity(); joints.len()],
+        };
+
+        Skin {
+            name: gltf_skin.name().map(String::from),
+            joints,
+            inverse_bind_matrices,
+        }
+    }
+
+    /// Read an animation's node transform channels. Morph target weight
+    /// channels and channels whose sampler can't be read are left out.
+    fn process_animation(&self, gltf_animation: &gltf::Animation) -> SceneAnimation {
+        let channels = gltf_animation
+            .channels()
+            .filter_map(|channel| {
+                let reader = channel.reader(|buffer| Some(&self.buffers[buffer.index()]));
+                let times: Vec<f32> = reader.read_inputs()?.collect();
+
+                let (property, values): (_, Vec<na::Vector4<f32>>) = match reader.read_outputs()? {
+                    gltf::animation::util::ReadOutputs::Translations(translations) => (
+                        AnimatedProperty::Translation,
+                        translations.map(|[x, y, z]| na::Vector4::new(x, y, z, 0.0)).collect(),
+                    ),
+                    gltf::animation::util::ReadOutputs::Rotations(rotations) => (
+                        AnimatedProperty::Rotation,
+                        rotations.into_f32().map(na::Vector4::from).collect(),
+                    ),
+                    gltf::animation::util::ReadOutputs::Scales(scales) => (
+                        AnimatedProperty::Scale,
+                        scales.map(|[x, y, z]| na::Vector4::new(x, y, z, 0.0)).collect(),
+                    ),
+                    gltf::animation::util::ReadOutputs::MorphTargetWeights(_) => return None,
+                };
+
+                let interpolation = channel.sampler().interpolation();
+                let values_per_keyframe = match interpolation {
+                    gltf::animation::Interpolation::CubicSpline => 3,
+                    _ => 1,
+                };
+                if values.len() != times.len() * values_per_keyframe {
+                    log::warn!(
+                        "gltf: skipping a channel of animation {} ({:?}): {} keyframes but {} values",
+                        gltf_animation.index(),
+                        gltf_animation.name().unwrap_or_default(),
+                        times.len(),
+                        values.len()
+                    );
+                    return None;
+                }
+
+                Some(AnimationChannel {
+                    node: channel.target().node().index(),
+                    property,
+                    interpolation,
+                    times,
+                    values,
+                })
+            })
+            .collect();
+
+        SceneAnimation {
+            name: gltf_animation.name().map(String::from),
+            channels,
+        }
     }
 
     fn process_node(
@@ -166,7 +442,13 @@ impl Gltf {
         gltf_node: &gltf::Node,
         parent_transform: na::Matrix4<f32>,
         primitive_data_map: &mut PrimitiveDataMap,
-    ) -> SrResult<(na::Matrix4<f32>, Option<vulkan_abstraction::gltf::Mesh>)> {
+        skipped_primitives: &mut Vec<SkippedPrimitive>,
+    ) -> SrResult<(
+        na::Matrix4<f32>,
+        Option<vulkan_abstraction::gltf::Mesh>,
+        Option<crate::Camera>,
+        Option<PunctualLight>,
+    )> {
         // the trasnform can also be given decomposed in: translation, rotation and scale
         // but the gltf crate takes care of this:
         // "If the transform is Decomposed, then the matrix is generated with the equation matrix = translation * rotation * scale."
@@ -174,29 +456,114 @@ impl Gltf {
 
         // I dont'use map because `?`` does not work inside a closure
         let mesh = match gltf_node.mesh() {
-            Some(gltf_mesh) => Some(self.process_mesh(gltf_mesh, primitive_data_map)?),
+            Some(gltf_mesh) => Some(self.process_mesh(gltf_mesh, primitive_data_map, skipped_primitives)?),
             None => None,
         };
 
-        if let Some(_camera) = gltf_node.camera() {
-            todo!()
-        }
+        let camera = gltf_node
+            .camera()
+            .map(|gltf_camera| Self::process_camera(&gltf_camera, &transform));
 
-        if let Some(_light) = gltf_node.light() {
-            todo!()
+        let light = gltf_node
+            .light()
+            .map(|gltf_light| Self::process_light(&gltf_light, &transform));
+
+        Ok((transform, mesh, camera, light))
+    }
+
+    /// Convert a `KHR_lights_punctual` light into a world-space [`PunctualLight`]
+    /// placed with the world transform of the node carrying it. As for
+    /// cameras, the light shines down the node's local `-Z`.
+    fn process_light(gltf_light: &gltf::khr_lights_punctual::Light, world_transform: &na::Matrix4<f32>) -> PunctualLight {
+        let position = world_transform.transform_point(&na::Point3::origin());
+        let direction = world_transform.transform_vector(&-na::Vector3::z()).normalize();
+        let color = gltf_light.color();
+        let intensity = gltf_light.intensity();
+
+        let mut light = PunctualLight {
+            position: position.coords.into(),
+            direction: direction.into(),
+            range: gltf_light.range().unwrap_or(0.0),
+            intensity: [color[0] * intensity, color[1] * intensity, color[2] * intensity],
+            ..Default::default()
+        };
+
+        match gltf_light.kind() {
+            gltf::khr_lights_punctual::Kind::Point => light.kind = PunctualLight::POINT,
+            gltf::khr_lights_punctual::Kind::Directional => light.kind = PunctualLight::DIRECTIONAL,
+            gltf::khr_lights_punctual::Kind::Spot {
+                inner_cone_angle,
+                outer_cone_angle,
+            } => {
+                light.kind = PunctualLight::SPOT;
+                light.inner_cone_cos = inner_cone_angle.cos();
+                light.outer_cone_cos = outer_cone_angle.cos();
+            }
         }
 
-        Ok((transform, mesh))
+        light
+    }
+
+    /// Convert a glTF camera into a [`crate::Camera`] placed with the world
+    /// transform of the node carrying it. The authored aspect ratio is ignored:
+    /// the renderer always uses the aspect of its output image.
+    fn process_camera(gltf_camera: &gltf::Camera, world_transform: &na::Matrix4<f32>) -> crate::Camera {
+        let (projection, fov_y, z_near, z_far) = match gltf_camera.projection() {
+            gltf::camera::Projection::Perspective(perspective) => (
+                crate::Projection::Perspective,
+                perspective.yfov().to_degrees(),
+                perspective.znear(),
+                // an infinite projection has no far plane: keep the default one,
+                // the ray tracer does not clip against it anyway
+                perspective.zfar(),
+            ),
+            gltf::camera::Projection::Orthographic(orthographic) => (
+                crate::Projection::Orthographic {
+                    y_mag: orthographic.ymag(),
+                },
+                crate::Camera::default().fov_y(),
+                orthographic.znear(),
+                Some(orthographic.zfar()),
+            ),
+        };
+        let z_far = z_far.unwrap_or_else(|| crate::Camera::default().z_far().max(z_near * 2.0));
+
+        crate::Camera::from_transform(world_transform, projection)
+            .set_fov_y(fov_y)
+            .set_clip_planes(z_near, z_far)
     }
 
     fn process_mesh(
         &self,
         gltf_mesh: gltf::Mesh,
         primitive_data_map: &mut PrimitiveDataMap,
+        skipped_primitives: &mut Vec<SkippedPrimitive>,
     ) -> SrResult<vulkan_abstraction::gltf::Mesh> {
         let mut primitives = vec![];
 
-        for (i, primitive) in gltf_mesh.primitives().filter(|p| Self::is_primitive_supported(p)).enumerate() {
+        for (i, primitive) in gltf_mesh.primitives().enumerate() {
+            if !Self::is_primitive_supported(&primitive) {
+                // a mesh instanced by several nodes is processed once per node: report it once
+                let already_reported = skipped_primitives
+                    .iter()
+                    .any(|skipped| skipped.mesh_index == gltf_mesh.index() && skipped.primitive_index == i);
+                if !already_reported {
+                    log::warn!(
+                        "gltf: skipping primitive {i} of mesh {} ({:?}): {:?} primitives are not supported",
+                        gltf_mesh.index(),
+                        gltf_mesh.name().unwrap_or_default(),
+                        primitive.mode()
+                    );
+                    skipped_primitives.push(SkippedPrimitive {
+                        mesh_index: gltf_mesh.index(),
+                        mesh_name: gltf_mesh.name().map(String::from),
+                        primitive_index: i,
+                        mode: primitive.mode(),
+                    });
+                }
+                continue;
+            }
+
             let vertex_position_accessor_index = primitive
                 .attributes()
                 .find(|(semantic, _)| *semantic == gltf::Semantic::Positions)
@@ -204,13 +571,6 @@ impl Gltf {
                 .1
                 .index();
 
-            let indices_accessor_index = match primitive.indices() {
-                Some(accessor) => accessor.index(),
-                None => i, // this is a cheap fix in the case that the primitive is a non-indexed geometry
-            };
-
-            let primitive_unique_key = (vertex_position_accessor_index, indices_accessor_index);
-
             let (material, tex_coords, local_emissive_triangles) = {
                 let material = primitive.material();
                 let material_pbr = primitive.material().pbr_metallic_roughness();
@@ -227,45 +587,104 @@ impl Gltf {
 
                 let ior = material.ior().unwrap_or(1.5);
 
+                let volume = material.volume();
+                let thickness_factor = volume.as_ref().map_or(0.0, |volume| volume.thickness_factor());
+                let attenuation_distance = volume.as_ref().map_or(f32::INFINITY, |volume| volume.attenuation_distance());
+                let attenuation_color = volume.as_ref().map_or([1.0; 3], |volume| volume.attenuation_color());
+
                 // The code is repeated because the type of the textures are not the same
                 // TODO: crate a macro
-                let (base_color_texture_index, base_color_tex_coord_index) =
+                let (base_color_texture_index, base_color_tex_coord_index, base_color_texture_transform) =
                     get_texture_indices!(material_pbr, base_color_texture);
-                let (metallic_roughness_texture_index, metallic_roughness_tex_coord_index) =
+                let (metallic_roughness_texture_index, metallic_roughness_tex_coord_index, metallic_roughness_texture_transform) =
                     get_texture_indices!(material_pbr, metallic_roughness_texture);
-                let (normal_texture_index, normal_tex_coord_index) = get_texture_indices!(material, normal_texture);
-                let (occlusion_texture_index, occlusion_tex_coord_index) = get_texture_indices!(material, occlusion_texture);
-                let (emissive_texture_index, emissive_tex_coord_index) = get_texture_indices!(material, emissive_texture);
+                let (normal_texture_index, normal_tex_coord_index, normal_texture_transform) =
+                    get_texture_indices!(material, normal_texture, json);
+                let (occlusion_texture_index, occlusion_tex_coord_index, occlusion_texture_transform) =
+                    get_texture_indices!(material, occlusion_texture, json);
+                let occlusion_strength = material.occlusion_texture().map_or(1.0, |texture| texture.strength());
+                let (emissive_texture_index, emissive_tex_coord_index, emissive_texture_transform) =
+                    get_texture_indices!(material, emissive_texture);
+
+                // gltf doesn't model KHR_materials_clearcoat and KHR_materials_sheen: read their JSON
+                let clearcoat = material.extension_value("KHR_materials_clearcoat");
+                let clearcoat_factor = json_f32(clearcoat, "clearcoatFactor").unwrap_or(0.0);
+                let clearcoat_roughness_factor = json_f32(clearcoat, "clearcoatRoughnessFactor").unwrap_or(0.0);
+                let (clearcoat_normal_texture_index, clearcoat_normal_tex_coord_index, clearcoat_normal_texture_transform) =
+                    match clearcoat.and_then(|clearcoat| clearcoat.get("clearcoatNormalTexture")) {
+                        Some(texture_info) => texture_info_from_json(texture_info),
+                        None => (None, 0, TextureTransform::default()),
+                    };
+
+                // the vertices have no set of coordinates for the clearcoat normal texture: it shares the normal texture's
+                let normal_tex_coord_index = match (normal_texture_index, clearcoat_normal_texture_index) {
+                    (None, Some(_)) => clearcoat_normal_tex_coord_index,
+                    (Some(_), Some(_)) if clearcoat_normal_tex_coord_index != normal_tex_coord_index => {
+                        log::warn!(
+                            "gltf: material {:?} samples its clearcoat normal texture with TEXCOORD_{normal_tex_coord_index}, the set of its normal texture, instead of TEXCOORD_{clearcoat_normal_tex_coord_index}",
+                            material.index()
+                        );
+                        normal_tex_coord_index
+                    }
+                    _ => normal_tex_coord_index,
+                };
+
+                let sheen = material.extension_value("KHR_materials_sheen");
+                let sheen_color_factor = json_f32_array(sheen, "sheenColorFactor").unwrap_or([0.0; 3]);
+                let sheen_roughness_factor = json_f32(sheen, "sheenRoughnessFactor").unwrap_or(0.0);
+
+                let specular = material.specular();
+                let specular_factor = specular.as_ref().map_or(1.0, |specular| specular.specular_factor());
+                let specular_color_factor = specular
+                    .as_ref()
+                    .map_or([1.0; 3], |specular| specular.specular_color_factor());
 
                 let pbr_metallic_roughness_properties = vulkan_abstraction::gltf::PbrMetallicRoughnessProperties {
                     base_color_factor,
                     metallic_factor,
                     roughness_factor,
                     base_color_texture_index,
+                    base_color_texture_transform,
                     metallic_roughness_texture_index,
+                    metallic_roughness_texture_transform,
                 };
 
                 let material = vulkan_abstraction::gltf::Material {
                     pbr_metallic_roughness_properties,
                     normal_texture_index,
+                    normal_texture_transform,
                     occlusion_texture_index,
+                    occlusion_texture_transform,
+                    occlusion_strength,
                     emissive_factor,
                     emissive_strength,
                     emissive_texture_index,
+                    emissive_texture_transform,
                     alpha_mode,
                     alpha_cutoff,
                     double_sided,
                     transmission_factor,
                     ior,
+                    thickness_factor,
+                    attenuation_distance,
+                    attenuation_color,
+                    clearcoat_factor,
+                    clearcoat_roughness_factor,
+                    clearcoat_normal_texture_index,
+                    clearcoat_normal_texture_transform,
+                    sheen_color_factor,
+                    sheen_roughness_factor,
+                    specular_factor,
+                    specular_color_factor,
                 };
 
-                let tex_coords = (
+                let tex_coords = [
                     base_color_tex_coord_index,
                     metallic_roughness_tex_coord_index,
                     normal_tex_coord_index,
                     occlusion_tex_coord_index,
                     emissive_tex_coord_index,
-                );
+                ];
 
                 let mut local_emissive_triangles = Vec::new();
 
@@ -276,11 +695,11 @@ impl Gltf {
 
                     let positions: Vec<_> = reader.read_positions().unwrap().collect();
 
-                    let indices: Vec<u32> = if let Some(iter) = reader.read_indices() {
-                        iter.into_u32().collect()
-                    } else {
-                        (0..positions.len() as u32).collect()
-                    };
+                    let indices = Self::triangle_list_indices(
+                        primitive.mode(),
+                        reader.read_indices().map(|iter| iter.into_u32().collect()),
+                        positions.len(),
+                    );
 
                     for chunk in indices.chunks_exact(3) {
                         let p0 = positions[chunk[0] as usize];
@@ -298,61 +717,109 @@ impl Gltf {
                 (material, tex_coords, local_emissive_triangles)
             };
 
+            // the spec asks for MikkTSpace tangents when they're missing but needed, i.e. when
+            // there's a normal texture to orient
+            let generated_tangents = primitive.get(&gltf::Semantic::Tangents).is_none()
+                && (material.normal_texture_index.is_some() || material.clearcoat_normal_texture_index.is_some());
+            let primitive_unique_key = PrimitiveUniqueKey {
+                positions: vertex_position_accessor_index,
+                indices: primitive.indices().map(|accessor| accessor.index()),
+                mode: primitive.mode().as_gl_enum(),
+                tex_coords,
+                generated_tangents,
+            };
+
             if let std::collections::hash_map::Entry::Vacant(e) = primitive_data_map.entry(primitive_unique_key) {
                 let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
 
                 let mut vertices: Vec<vulkan_abstraction::gltf::Vertex> = vec![];
 
                 let positions: Vec<_> = reader.read_positions().unwrap().collect();
-                let normals: Vec<_> = reader.read_normals().unwrap().collect();
-
-                let tangents: Vec<[f32; 4]> = reader
-                    .read_tangents()
-                    .map_or_else(|| vec![[0.0, 0.0, 0.0, 0.0]; positions.len()], |iter| iter.collect());
+                let normals: Option<Vec<_>> = reader.read_normals().map(|iter| iter.collect());
+                let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(|iter| iter.collect());
 
                 for i in 0..positions.len() {
                     vertices.push(vulkan_abstraction::gltf::Vertex {
                         position: positions[i],
-                        normal: normals[i],
-                        tangent: tangents[i],
+                        normal: normals.as_ref().map_or([0.0; 3], |normals| normals[i]),
+                        tangent: tangents.as_ref().map_or([0.0; 4], |tangents| tangents[i]),
                         ..Default::default()
                     });
                 }
 
-                let index_buffer = {
-                    let indices = if primitive.indices().is_some() {
-                        // get vertices index
-
-                        reader.read_indices().unwrap().into_u32().collect::<Vec<_>>()
-                    } else {
-                        // if the primitive is a non-indexed geometry we create the indices
-
-                        (0..vertices.len() as u32 / 3).collect::<Vec<_>>()
-                    };
-
-                    vulkan_abstraction::IndexBuffer::new_for_blas_from_data(Rc::clone(&self.core), &indices)?
-                };
+                let mut indices = Self::triangle_list_indices(
+                    primitive.mode(),
+                    reader.read_indices().map(|iter| iter.into_u32().collect()),
+                    vertices.len(),
+                );
 
                 // This could also be done with zip, but the code would be equally long and with a lot of nested tuples
                 // I thought of moving the zip operation to a separate function but the type of reader doesn't allow you to pass it around
-                insert_tex_coords!(reader, vertices, tex_coords.0, base_color_tex_coord);
-                insert_tex_coords!(reader, vertices, tex_coords.1, metallic_roughness_tex_coord);
-                insert_tex_coords!(reader, vertices, tex_coords.2, normal_tex_coord);
-                insert_tex_coords!(reader, vertices, tex_coords.3, occlusion_tex);
-                insert_tex_coords!(reader, vertices, tex_coords.4, emissive_tex);
+                insert_tex_coords!(reader, vertices, tex_coords[0], base_color_tex_coord);
+                insert_tex_coords!(reader, vertices, tex_coords[1], metallic_roughness_tex_coord);
+                insert_tex_coords!(reader, vertices, tex_coords[2], normal_tex_coord);
+                insert_tex_coords!(reader, vertices, tex_coords[3], occlusion_tex);
+                insert_tex_coords!(reader, vertices, tex_coords[4], emissive_tex);
 
-                let vertex_buffer = vulkan_abstraction::VertexBuffer::new_for_blas_from_data(Rc::clone(&self.core), &vertices)?;
-
-                let primitive_data = vulkan_abstraction::gltf::PrimitiveData {
-                    vertex_buffer,
-                    index_buffer,
+                // only the first set of joints and weights is supported: four influences per vertex
+                let mut skin_vertices: Vec<SkinVertex> = match (reader.read_joints(0), reader.read_weights(0)) {
+                    (Some(joints), Some(weights)) => joints
+                        .into_u16()
+                        .zip(weights.into_f32())
+                        .map(|(joints, weights)| SkinVertex {
+                            joints: joints.map(u32::from),
+                            weights,
+                        })
+                        .collect(),
+                    _ => vec![],
                 };
 
-                e.insert(primitive_data);
+                let mut morph_deltas: Vec<MorphDelta> = vec![];
+                for (positions, normals, tangents) in reader.read_morph_targets() {
+                    let mut target = vec![MorphDelta::default(); vertices.len()];
+                    if let Some(positions) = positions {
+                        target.iter_mut().zip(positions).for_each(|(delta, d)| delta.position = d);
+                    }
+                    if let Some(normals) = normals {
+                        target.iter_mut().zip(normals).for_each(|(delta, d)| delta.normal = d);
+                    }
+                    if let Some(tangents) = tangents {
+                        target.iter_mut().zip(tangents).for_each(|(delta, d)| delta.tangent = d);
+                    }
+                    morph_deltas.extend(target);
+                }
+
+                // the spec asks for flat normals when they're missing, smooth ones are opted into
+                if normals.is_none() && self.normal_generation == NormalGeneration::Smooth {
+                    generate_smooth_normals(&mut vertices, &indices);
+                } else if normals.is_none() {
+                    // unwelding gives every triangle corner its own vertex: the skin and the morph targets follow
+                    if !skin_vertices.is_empty() {
+                        skin_vertices = indices.iter().map(|&i| skin_vertices[i as usize]).collect();
+                    }
+                    if !morph_deltas.is_empty() {
+                        morph_deltas = morph_deltas
+                            .chunks(vertices.len())
+                            .flat_map(|target| indices.iter().map(|&i| target[i as usize]))
+                            .collect();
+                    }
+                    (vertices, indices) = generate_flat_normals(&vertices, &indices);
+                }
+                if generated_tangents {
+                    generate_tangents(&mut vertices, &indices);
+                }
+
+                e.insert(vulkan_abstraction::gltf::PrimitiveData {
+                    vertices,
+                    indices,
+                    skin_vertices,
+                    morph_deltas,
+                });
             }
             primitives.push(vulkan_abstraction::gltf::Primitive {
                 unique_key: primitive_unique_key,
                 material,
+                morph_target_count: primitive.morph_targets().len(),
                 local_emissive_triangles,
             });
         }
@@ -361,13 +828,686 @@ impl Gltf {
     }
 
     fn is_primitive_supported(primitive: &gltf::Primitive) -> bool {
-        match primitive.mode() {
-            gltf::mesh::Mode::Triangles => true,
-            m => {
-                log::error!("Found unsupported primitive mode: {:?}", m);
+        matches!(
+            primitive.mode(),
+            gltf::mesh::Mode::Triangles | gltf::mesh::Mode::TriangleStrip | gltf::mesh::Mode::TriangleFan
+        )
+    }
 
-                false
+    /// Turn the indices of a triangle primitive (`None` for non-indexed
+    /// geometry, whose vertices are used in order) into a triangle list.
+    /// Strips and fans are unrolled following the glTF spec's vertex order, so
+    /// the winding is preserved; the degenerate triangles strips use as
+    /// restarts are dropped.
+    fn triangle_list_indices(mode: gltf::mesh::Mode, indices: Option<Vec<u32>>, vertex_count: usize) -> Vec<u32> {
+        let indices = indices.unwrap_or_else(|| (0..vertex_count as u32).collect());
+        let triangle_count = indices.len().saturating_sub(2);
+
+        let triangles: Vec<[u32; 3]> = match mode {
+            gltf::mesh::Mode::TriangleStrip => (0..triangle_count)
+                .map(|i| {
+                    let flip = i % 2;
+                    [indices[i], indices[i + 1 + flip], indices[i + 2 - flip]]
+                })
+                .collect(),
+            gltf::mesh::Mode::TriangleFan => (0..triangle_count)
+                .map(|i| [indices[i + 1], indices[i + 2], indices[0]])
+                .collect(),
+            _ => return indices,
+        };
+
+        triangles
+            .into_iter()
+            .filter(|[a, b, c]| a != b && b != c && a != c)
+            .flatten()
+            .collect()
+    }
+}
+
+fn is_data_uri(uri: &str) -> bool {
+    uri.starts_with("data:")
+}
+
+/// The index of the image `texture` samples: the KTX2 image of its
+/// `KHR_texture_basisu` extension if it has one, its PNG/JPEG source otherwise.
+///
+/// Textures without a `source` (allowed when the extension is required) are
+/// rejected by the gltf crate before getting here.
+fn texture_source(texture: &gltf::Texture, image_count: usize) -> usize {
+    texture
+        .extension_value("KHR_texture_basisu")
+        .and_then(|basisu| basisu.get("source")?.as_u64())
+        .map(|source| source as usize)
+        .filter(|&source| source < image_count)
+        .unwrap_or(texture.source().index())
+}
+
+/// Record the encoding every texture slot of the primitives under `node`
+/// samples its texture with, by texture index.
+fn collect_texture_encodings(node: &Node, encodings: &mut HashMap<usize, HashSet<TexelEncoding>>) {
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            for (texture_index, encoding) in primitive.material.texture_indices() {
+                if let Some(texture_index) = texture_index {
+                    encodings.entry(texture_index).or_default().insert(encoding);
+                }
             }
         }
     }
+
+    for child in node.children().iter().flatten() {
+        collect_texture_encodings(child, encodings);
+    }
+}
+
+/// Read a `KHR_texture_transform` object: the transform, and the set of
+/// coordinates it overrides the texture's with. Missing or malformed members
+/// keep their default.
+fn texture_transform_from_json(value: &gltf::json::Value) -> (TextureTransform, Option<u32>) {
+    let default = TextureTransform::default();
+    let transform = TextureTransform {
+        offset: json_f32_array(Some(value), "offset").unwrap_or(default.offset),
+        rotation: json_f32(Some(value), "rotation").unwrap_or(default.rotation),
+        scale: json_f32_array(Some(value), "scale").unwrap_or(default.scale),
+    };
+    let tex_coord = value
+        .get("texCoord")
+        .and_then(|tex_coord| tex_coord.as_u64())
+        .map(|t| t as u32);
+    (transform, tex_coord)
+}
+
+/// Read a texture info object of an extension gltf doesn't model: the texture
+/// index, the set of coordinates and the `KHR_texture_transform`.
+fn texture_info_from_json(value: &gltf::json::Value) -> (Option<usize>, u32, TextureTransform) {
+    let index = value
+        .get("index")
+        .and_then(|index| index.as_u64())
+        .map(|index| index as usize);
+    let tex_coord = value
+        .get("texCoord")
+        .and_then(|tex_coord| tex_coord.as_u64())
+        .map_or(0, |t| t as u32);
+    let (transform, transform_tex_coord) = value
+        .get("extensions")
+        .and_then(|extensions| extensions.get("KHR_texture_transform"))
+        .map(texture_transform_from_json)
+        .unwrap_or_default();
+
+    (index, transform_tex_coord.unwrap_or(tex_coord), transform)
+}
+
+/// The number under `key` of an extension's JSON object.
+fn json_f32(object: Option<&gltf::json::Value>, key: &str) -> Option<f32> {
+    object?.get(key)?.as_f64().map(|value| value as f32)
+}
+
+/// The array of `N` numbers under `key` of an extension's JSON object.
+fn json_f32_array<const N: usize>(object: Option<&gltf::json::Value>, key: &str) -> Option<[f32; N]> {
+    let values = object?.get(key)?.as_array()?;
+    let values: Vec<f32> = values
+        .iter()
+        .map(|value| value.as_f64().map(|v| v as f32))
+        .collect::<Option<_>>()?;
+    values.try_into().ok()
+}
+
+/// Decode `(image index, name, encoded bytes)` triples, split over as many
+/// threads as the machine runs in parallel.
+fn decode_images(encoded_images: &[(usize, String, Vec<u8>)]) -> SrResult<Vec<(usize, Image)>> {
+    let threads = std::thread::available_parallelism().map_or(1, usize::from);
+    let chunk_size = encoded_images.len().div_ceil(threads).max(1);
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = encoded_images
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .map(|(index, name, bytes)| Ok((*index, decode_image(bytes, name)?)))
+                        .collect::<SrResult<Vec<_>>>()
+                })
+            })
+            .collect();
+
+        let mut images = Vec::with_capacity(encoded_images.len());
+        for worker in workers {
+            let decoded = worker
+                .join()
+                .map_err(|_| SrError::new_custom("gltf: an image decoding thread panicked".to_string()))?;
+            images.extend(decoded?);
+        }
+        Ok(images)
+    })
+}
+
+/// Decode an image of the document: a KTX2 image is read as is, a PNG or
+/// JPEG is decoded into the pixel formats `gltf::import` decodes images into.
+/// (`::image` is the crate, not the module of this one.)
+fn decode_image(bytes: &[u8], name: &str) -> SrResult<Image> {
+    if Image::is_ktx2(bytes) {
+        return Image::from_ktx2(bytes, name);
+    }
+
+    let decoded =
+        ::image::load_from_memory(bytes).map_err(|e| SrError::new_custom(format!("gltf: cannot decode image {name:?}: {e}")))?;
+
+    let format = match &decoded {
+        ::image::DynamicImage::ImageLuma8(_) => gltf::image::Format::R8,
+        ::image::DynamicImage::ImageLumaA8(_) => gltf::image::Format::R8G8,
+        ::image::DynamicImage::ImageRgb8(_) => gltf::image::Format::R8G8B8,
+        ::image::DynamicImage::ImageRgba8(_) => gltf::image::Format::R8G8B8A8,
+        ::image::DynamicImage::ImageLuma16(_) => gltf::image::Format::R16,
+        ::image::DynamicImage::ImageLumaA16(_) => gltf::image::Format::R16G16,
+        ::image::DynamicImage::ImageRgb16(_) => gltf::image::Format::R16G16B16,
+        ::image::DynamicImage::ImageRgba16(_) => gltf::image::Format::R16G16B16A16,
+        ::image::DynamicImage::ImageRgb32F(_) => gltf::image::Format::R32G32B32FLOAT,
+        ::image::DynamicImage::ImageRgba32F(_) => gltf::image::Format::R32G32B32A32FLOAT,
+        // any other layout is expanded to RGBA8
+        _ => {
+            let rgba = decoded.into_rgba8();
+            return Ok(Image {
+                height: rgba.height() as usize,
+                width: rgba.width() as usize,
+                pixels: ImagePixels::Decoded {
+                    format: gltf::image::Format::R8G8B8A8,
+                    raw_data: rgba.into_raw(),
+                },
+                encoding: TexelEncoding::Linear,
+            });
+        }
+    };
+
+    Ok(Image {
+        height: decoded.height() as usize,
+        width: decoded.width() as usize,
+        pixels: ImagePixels::Decoded {
+            format,
+            raw_data: decoded.into_bytes(),
+        },
+        encoding: TexelEncoding::Linear,
+    })
+}
+
+/// Loads one of the hand-written scenes in `tests/gltf` and lays it out the
+/// way `Scene::load_into_gpu` does, without a GPU.
+#[cfg(test)]
+pub(crate) fn load_test_scene(name: &str) -> (crate::Scene, crate::SceneData) {
+    let path = format!("{}/tests/gltf/{name}", env!("CARGO_MANIFEST_DIR"));
+    let gltf = Gltf::new(&path).unwrap_or_else(|e| panic!("loading {path} failed: {e}"));
+    gltf.create_default_scene().expect("create_default_scene failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indices_of(scene_data: &crate::SceneData, key: &PrimitiveUniqueKey) -> Vec<u32> {
+        scene_data.primitive_data_map[key].indices.clone()
+    }
+
+    fn translation_x(transform: &ash::vk::TransformMatrixKHR) -> f32 {
+        transform.matrix[3]
+    }
+
+    #[test]
+    fn indexed_primitive_keeps_its_indices() {
+        let (scene, scene_data) = load_test_scene("indexed.gltf");
+        let layout = scene.layout();
+
+        assert_eq!(layout.primitives.len(), 1);
+        assert_eq!(layout.instances.len(), 1);
+        let key = layout.primitives[0].unique_key;
+        assert_eq!(key.indices, Some(2));
+        assert_eq!(scene_data.primitive_data_map.len(), 1);
+        assert_eq!(scene_data.primitive_data_map[&key].vertices.len(), 4);
+        assert_eq!(indices_of(&scene_data, &key), vec![0, 1, 2, 0, 2, 3]);
+    }
+
+    /// The non-indexed primitive reads `POSITION` from accessor 1 and is
+    /// primitive 0 of its mesh, while the indexed one reads the same positions
+    /// with indices from accessor 0: the dedup key must keep them apart.
+    #[test]
+    fn non_indexed_primitive_gets_sequential_indices() {
+        let (scene, scene_data) = load_test_scene("non_indexed.gltf");
+        let layout = scene.layout();
+
+        assert_eq!(layout.primitives.len(), 2);
+        assert_eq!(layout.instances.iter().map(|(blas, _)| *blas).collect::<Vec<_>>(), vec![0, 1]);
+
+        let non_indexed = layout.primitives[0].unique_key;
+        let indexed = layout.primitives[1].unique_key;
+        assert_eq!(non_indexed.positions, 1);
+        assert_eq!(non_indexed.indices, None);
+        assert_eq!(indexed.positions, 1);
+        assert_eq!(indexed.indices, Some(0));
+
+        assert_eq!(indices_of(&scene_data, &non_indexed), (0..6).collect::<Vec<_>>());
+        assert_eq!(indices_of(&scene_data, &indexed), vec![5, 4, 3]);
+    }
+
+    /// Two meshes with the same accessors and mode share one BLAS, whichever
+    /// node instances them; the same accessors drawn as a strip don't.
+    #[test]
+    fn shared_accessors_share_a_blas() {
+        let (scene, scene_data) = load_test_scene("shared_accessors.gltf");
+        let layout = scene.layout();
+
+        assert_eq!(layout.primitives.len(), 2);
+        assert_eq!(scene_data.primitive_data_map.len(), 2);
+        assert_eq!(
+            layout.instances.iter().map(|(blas, _)| *blas).collect::<Vec<_>>(),
+            vec![0, 0, 0, 1]
+        );
+        assert_eq!(
+            layout
+                .instances
+                .iter()
+                .map(|(_, transform)| translation_x(transform))
+                .collect::<Vec<_>>(),
+            vec![0.0, 2.0, 4.0, 6.0]
+        );
+
+        let fan = layout.primitives[0].unique_key;
+        let strip = layout.primitives[1].unique_key;
+        assert_eq!(fan.mode, gltf::mesh::Mode::TriangleFan.as_gl_enum());
+        assert_eq!(strip.mode, gltf::mesh::Mode::TriangleStrip.as_gl_enum());
+        assert_eq!(indices_of(&scene_data, &fan), vec![1, 2, 0, 2, 3, 0]);
+        assert_eq!(indices_of(&scene_data, &strip), vec![0, 1, 2, 1, 3, 2]);
+    }
+
+    /// Missing normals are flat by default, unwelding the triangles; smooth
+    /// ones average the normals of the triangles sharing a vertex.
+    #[test]
+    fn missing_normals_are_generated_flat_or_smooth() {
+        let path = format!("{}/tests/gltf/missing_normals.gltf", env!("CARGO_MANIFEST_DIR"));
+        let mut gltf = Gltf::new(&path).unwrap();
+        let vertices = |gltf: &Gltf| {
+            let (scene, scene_data) = gltf.create_default_scene().unwrap();
+            scene_data.primitive_data_map[&scene.layout().primitives[0].unique_key]
+                .vertices
+                .iter()
+                .map(|vertex| vertex.normal)
+                .collect::<Vec<_>>()
+        };
+
+        let flat = vertices(&gltf);
+        assert_eq!(flat.len(), 6);
+        assert_eq!(flat[..3], [[0.0, 0.0, 1.0]; 3]);
+        assert_eq!(flat[3..], [[0.0, 1.0, 0.0]; 3]);
+
+        gltf.set_normal_generation(NormalGeneration::Smooth);
+        let smooth = vertices(&gltf);
+        assert_eq!(smooth.len(), 4);
+        let shared = std::f32::consts::FRAC_1_SQRT_2;
+        for (normal, expected) in
+            smooth
+                .iter()
+                .zip([[0.0, shared, shared], [0.0, shared, shared], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]])
+        {
+            assert!(normal.iter().zip(expected).all(|(n, e)| (n - e).abs() < 1e-5), "{smooth:?}");
+        }
+    }
+
+    /// The same accessors under materials that build different vertices don't
+    /// share them: only the normal-mapped primitive gets tangents, whichever
+    /// primitive comes first.
+    #[test]
+    fn materials_building_different_vertices_keep_them_apart() {
+        let (scene, scene_data) = load_test_scene("shared_geometry_materials.gltf");
+        let layout = scene.layout();
+
+        assert_eq!(layout.primitives.len(), 3);
+        assert_eq!(scene_data.primitive_data_map.len(), 3);
+        let [plain, normal_mapped, _] = [0, 1, 2].map(|i| layout.primitives[i].unique_key);
+        assert_eq!((plain.positions, normal_mapped.positions), (0, 0));
+        assert!(!plain.generated_tangents);
+        assert!(normal_mapped.generated_tangents);
+
+        let plain_vertices = &scene_data.primitive_data_map[&plain].vertices;
+        assert!(plain_vertices.iter().all(is_tangent_missing));
+        for vertex in &scene_data.primitive_data_map[&normal_mapped].vertices {
+            let tangent = vertex.tangent;
+            assert!((tangent[0] - 1.0).abs() < 1e-5 && tangent[1].abs() < 1e-5, "{tangent:?}");
+        }
+    }
+
+    /// A `KHR_texture_transform` `texCoord` override on shared geometry reads
+    /// its set into vertices of its own, leaving the other materials' alone.
+    #[test]
+    fn tex_coord_overrides_get_their_own_vertices() {
+        let (scene, scene_data) = load_test_scene("shared_geometry_materials.gltf");
+        let layout = scene.layout();
+
+        let plain = layout.primitives[0].unique_key;
+        let overridden = layout.primitives[2].unique_key;
+        assert_eq!(plain.tex_coords[0], 0);
+        assert_eq!(overridden.tex_coords[0], 1);
+
+        let base_color_uv = |key: &PrimitiveUniqueKey| {
+            let vertex = scene_data.primitive_data_map[key].vertices[1];
+            vertex.base_color_tex_coord
+        };
+        assert_eq!(base_color_uv(&plain), [1.0, 0.0]);
+        assert_eq!(base_color_uv(&overridden), [0.75, 0.25]);
+    }
+
+    /// A node with a skin is laid out as a skinned instance of its own. The
+    /// scene is authored in the bind pose, and the instance is placed with its
+    /// node's transform, which the joint matrices must then undo.
+    #[test]
+    fn skinned_node_gets_its_own_instance() {
+        let (scene, scene_data) = load_test_scene("skinned.gltf");
+        let layout = scene.layout();
+
+        assert!(layout.primitives.is_empty());
+        assert!(layout.instances.is_empty());
+        assert_eq!(layout.deformed_instances.len(), 1);
+        assert_eq!(scene.skins()[0].joints, vec![1, 2]);
+
+        let instance = &layout.deformed_instances[0];
+        assert_eq!((instance.node, instance.skin), (0, Some(0)));
+        assert!(instance.morph_weights.is_empty());
+        assert_eq!(translation_x(&instance.transform), 2.0);
+
+        let undo_node = na::Matrix4::new_translation(&na::Vector3::new(-2.0, 0.0, 0.0));
+        assert_eq!(instance.joint_matrices.len(), 2);
+        for joint_matrix in &instance.joint_matrices {
+            assert!((joint_matrix - undo_node).abs().max() < 1e-6, "{joint_matrix}");
+        }
+
+        let skin_vertices = &scene_data.primitive_data_map[&instance.primitive.unique_key].skin_vertices;
+        assert_eq!(skin_vertices.len(), 3);
+        assert_eq!(
+            skin_vertices[1],
+            SkinVertex {
+                joints: [0, 1, 0, 0],
+                weights: [0.5, 0.5, 0.0, 0.0],
+            }
+        );
+    }
+
+    #[test]
+    fn scenes_are_listed_and_selected() {
+        let path = format!("{}/tests/gltf/scenes.gltf", env!("CARGO_MANIFEST_DIR"));
+        let gltf = Gltf::new(&path).unwrap();
+
+        assert_eq!(
+            gltf.scenes(),
+            vec![
+                SceneInfo {
+                    index: 0,
+                    name: Some("empty".to_string()),
+                    node_count: 0,
+                    is_default: false,
+                },
+                SceneInfo {
+                    index: 1,
+                    name: Some("triangle".to_string()),
+                    node_count: 2,
+                    is_default: true,
+                },
+            ]
+        );
+
+        assert_eq!(gltf.scene_index(&SceneSelector::Default).unwrap(), 1);
+        assert_eq!(gltf.scene_index(&SceneSelector::Name("empty".to_string())).unwrap(), 0);
+        assert!(gltf.scene_index(&SceneSelector::Name("missing".to_string())).is_err());
+        assert!(gltf.scene_index(&SceneSelector::Index(2)).is_err());
+
+        let (empty, _) = gltf.create_scene(0).unwrap();
+        assert!(empty.nodes().is_empty());
+    }
+
+    /// The buffer of `indexed.gltf`, moved out of its data URI into a file
+    /// only a resolver can supply, loads the same as the original.
+    #[test]
+    fn slice_loading_resolves_external_buffers() {
+        let path = format!("{}/tests/gltf/indexed.gltf", env!("CARGO_MANIFEST_DIR"));
+        let embedded = std::fs::read_to_string(&path).unwrap();
+        let buffer = Gltf::new(&path).unwrap().buffers[0].0.clone();
+
+        let uri_start = embedded.find("data:").unwrap();
+        let uri_end = uri_start + embedded[uri_start..].find('"').unwrap();
+        let external = format!("{}geometry.bin{}", &embedded[..uri_start], &embedded[uri_end..]);
+
+        let resolver = |uri: &str| -> SrResult<Vec<u8>> {
+            assert_eq!(uri, "geometry.bin");
+            Ok(buffer.clone())
+        };
+        let (scene, scene_data) = Gltf::from_slice(external.as_bytes(), &resolver)
+            .and_then(|gltf| gltf.create_default_scene())
+            .unwrap();
+        let key = scene.layout().primitives[0].unique_key;
+        assert_eq!(indices_of(&scene_data, &key), vec![0, 1, 2, 0, 2, 3]);
+
+        assert!(Gltf::from_slice(embedded.as_bytes(), &EmbeddedOnly).is_ok());
+        assert!(Gltf::from_slice(external.as_bytes(), &EmbeddedOnly).is_err());
+    }
+
+    /// Morph weights are per instance: each node instancing a mesh with morph
+    /// targets gets a BLAS of its own, blended with the node's weights, else
+    /// the mesh's.
+    #[test]
+    fn morph_targets_give_each_instance_its_weights() {
+        let (scene, scene_data) = load_test_scene("morph_targets.gltf");
+        let layout = scene.layout();
+
+        assert!(layout.primitives.is_empty());
+        assert_eq!(
+            layout
+                .deformed_instances
+                .iter()
+                .map(|instance| (instance.skin, instance.morph_weights.clone()))
+                .collect::<Vec<_>>(),
+            vec![(None, vec![0.5, 0.0]), (None, vec![0.0, 1.0])]
+        );
+
+        let primitive = layout.deformed_instances[0].primitive;
+        assert_eq!(primitive.morph_target_count, 2);
+
+        // target-major: target 0 lifts vertex 2 along z, target 1 moves vertex 1 along x
+        let deltas = &scene_data.primitive_data_map[&primitive.unique_key].morph_deltas;
+        assert_eq!(deltas.len(), 6);
+        assert_eq!(deltas[2].position, [0.0, 0.0, 1.0]);
+        assert_eq!(deltas[3 + 1].position, [1.0, 0.0, 0.0]);
+        assert_eq!(deltas[3 + 2].position, [0.0; 3]);
+    }
+
+    /// KHR_texture_transform is read on every kind of texture slot, and its
+    /// texCoord picks the set of coordinates the slot's vertices carry.
+    #[test]
+    fn texture_transforms_are_read_per_slot() {
+        let (scene, scene_data) = load_test_scene("texture_transform.gltf");
+        let layout = scene.layout();
+        let material = &layout.primitives[0].material;
+
+        let base_color = material.pbr_metallic_roughness_properties.base_color_texture_transform;
+        assert_eq!((base_color.offset, base_color.scale), ([0.5, 0.0], [2.0, 2.0]));
+        assert_eq!(base_color.rows(), [[2.0, 0.0, 0.5, 0.0], [0.0, 2.0, 0.0, 0.0]]);
+
+        let normal = material.normal_texture_transform.rows();
+        let expected = [[0.0, 4.0, 0.0, 0.0], [-4.0, 0.0, 0.0, 0.0]];
+        for (row, expected) in normal.iter().zip(expected) {
+            for (value, expected) in row.iter().zip(expected) {
+                assert!((value - expected).abs() < 1e-5, "{normal:?}");
+            }
+        }
+        assert_eq!(material.emissive_texture_transform, TextureTransform::default());
+        assert_eq!(material.occlusion_texture_index, Some(0));
+        assert_eq!(material.occlusion_strength, 0.5);
+
+        let vertices = &scene_data.primitive_data_map[&layout.primitives[0].unique_key].vertices;
+        assert_eq!(
+            { vertices[1].base_color_tex_coord },
+            [0.75, 0.25],
+            "the transform's texCoord wins"
+        );
+        assert_eq!({ vertices[1].normal_tex_coord }, [1.0, 0.0]);
+        assert_eq!({ vertices[1].emissive_tex }, [0.75, 0.25]);
+    }
+
+    /// A `KHR_texture_basisu` texture samples its KTX2 image, whose mip levels
+    /// are kept as stored, and its PNG fallback is never decoded.
+    #[test]
+    fn basisu_textures_sample_their_ktx2_image() {
+        let (_, scene_data) = load_test_scene("basisu.gltf");
+
+        assert_eq!(scene_data.images.len(), 2, "the fallback is skipped");
+        assert_eq!(
+            (scene_data.textures[0].source, scene_data.textures[0].srgb_source),
+            (None, Some(0))
+        );
+        assert_eq!(
+            (scene_data.textures[1].source, scene_data.textures[1].srgb_source),
+            (Some(1), None)
+        );
+
+        let ktx2 = &scene_data.images[0];
+        assert_eq!((ktx2.width, ktx2.height), (2, 2));
+        assert_eq!(ktx2.encoding, TexelEncoding::Srgb, "base color");
+        match &ktx2.pixels {
+            ImagePixels::Levels { format, levels } => {
+                assert_eq!(*format, ash::vk::Format::R8G8B8A8_UNORM);
+                assert_eq!(levels.len(), 2);
+                assert_eq!(levels[0].len(), 16);
+                assert_eq!(levels[1], [128, 128, 128, 255]);
+            }
+            _ => panic!("the KTX2 image has a Vulkan format"),
+        }
+        assert!(matches!(scene_data.images[1].pixels, ImagePixels::Decoded { .. }));
+        assert_eq!(scene_data.images[1].encoding, TexelEncoding::Linear, "normals");
+    }
+
+    /// An image sampled both as color (base color, emissive) and as data
+    /// (normals, occlusion) is uploaded twice, once per encoding; each slot
+    /// resolves to its own upload.
+    #[test]
+    fn images_are_uploaded_once_per_encoding() {
+        let (_, scene_data) = load_test_scene("texture_transform.gltf");
+
+        assert_eq!(scene_data.images.len(), 2);
+        let texture = &scene_data.textures[0];
+        let (Some(linear), Some(srgb)) = (texture.source, texture.srgb_source) else {
+            panic!("the texture is sampled both ways");
+        };
+        assert_ne!(linear, srgb);
+        assert_eq!(scene_data.images[linear].encoding, TexelEncoding::Linear);
+        assert_eq!(scene_data.images[srgb].encoding, TexelEncoding::Srgb);
+    }
+
+    /// Decoded texels widened for upload get an opaque alpha, gray is spread
+    /// over the color channels and 16-bit channels are rescaled to 8 bits.
+    #[test]
+    fn decoded_texels_are_converted_for_upload() {
+        use ash::vk::Format;
+
+        let rgb = [10, 20, 30, 40, 50, 60];
+        assert_eq!(
+            convert_decoded(gltf::image::Format::R8G8B8, &rgb, Format::R8G8B8A8_SRGB),
+            Some(vec![10, 20, 30, 255, 40, 50, 60, 255])
+        );
+
+        let gray_alpha = [0xFFFFu16, 0x8000].map(u16::to_ne_bytes).concat();
+        assert_eq!(
+            convert_decoded(gltf::image::Format::R16G16, &gray_alpha, Format::R8G8B8A8_UNORM),
+            Some(vec![255, 255, 255, 128])
+        );
+        assert_eq!(
+            convert_decoded(gltf::image::Format::R8, &[1], Format::R16G16B16A16_UNORM),
+            Some([257u16, 257, 257, 0xFFFF].map(u16::to_ne_bytes).concat())
+        );
+
+        assert_eq!(
+            convert_decoded(gltf::image::Format::R8G8B8, &rgb, Format::R32G32B32A32_SFLOAT),
+            None,
+            "normalized texels are not converted to float"
+        );
+    }
+
+    /// Mip levels average their 2x2 texels, sRGB colors in linear space, down
+    /// to 1x1; an odd side rounds down.
+    #[test]
+    fn mip_chains_average_texels_in_linear_space() {
+        use ash::vk::Format;
+
+        let checker = [[0, 0, 0, 255], [255, 255, 255, 255], [255, 255, 255, 255], [0, 0, 0, 255]].concat();
+        assert_eq!(
+            mip_chain(checker.clone(), 2, 2, Format::R8G8B8A8_UNORM),
+            vec![checker, vec![128, 128, 128, 255]]
+        );
+
+        let black_white = [[0, 0, 0, 255], [255, 255, 255, 255]].concat();
+        assert_eq!(
+            mip_chain(black_white.clone(), 2, 1, Format::R8G8B8A8_SRGB),
+            vec![black_white, vec![188, 188, 188, 255]]
+        );
+
+        let levels = mip_chain(vec![0; 3 * 4], 3, 1, Format::R8G8B8A8_UNORM);
+        assert_eq!(levels.iter().map(Vec::len).collect::<Vec<_>>(), [12, 4]);
+    }
+
+    /// The clearcoat, sheen, specular and volume extensions are read, and a
+    /// material without them gets a plain, thin-walled surface.
+    #[test]
+    fn material_extensions_are_read() {
+        let (scene, _) = load_test_scene("material_extensions.gltf");
+        let layout = scene.layout();
+        let material = &layout.primitives[0].material;
+        assert_eq!((material.clearcoat_factor, material.clearcoat_roughness_factor), (1.0, 0.25));
+        assert_eq!(material.clearcoat_normal_texture_index, None);
+        assert_eq!(material.sheen_color_factor, [0.5, 0.25, 1.0]);
+        assert_eq!(material.sheen_roughness_factor, 0.5);
+        assert_eq!(material.specular_factor, 0.5);
+        assert_eq!(material.specular_color_factor, [1.0, 0.5, 0.25]);
+        assert_eq!(material.transmission_factor, 1.0);
+        assert_eq!((material.thickness_factor, material.attenuation_distance), (0.1, 2.0));
+        assert_eq!(material.attenuation_color, [0.5, 0.75, 1.0]);
+
+        let (scene, _) = load_test_scene("indexed.gltf");
+        let layout = scene.layout();
+        let material = &layout.primitives[0].material;
+        assert_eq!(material.clearcoat_factor, 0.0);
+        assert_eq!(material.sheen_color_factor, [0.0; 3]);
+        assert_eq!((material.specular_factor, material.specular_color_factor), (1.0, [1.0; 3]));
+        assert_eq!(
+            (material.thickness_factor, material.attenuation_distance),
+            (0.0, f32::INFINITY)
+        );
+    }
+
+    /// Masked and blended materials keep their mode and cutoff (0.5 when
+    /// unset), and only opaque ones let the BLAS skip the any-hit shader.
+    /// Sidedness comes along with them (single-sided unless set).
+    #[test]
+    fn alpha_modes_are_read() {
+        let (scene, _) = load_test_scene("alpha_modes.gltf");
+        let layout = scene.layout();
+        let modes = layout
+            .primitives
+            .iter()
+            .map(|primitive| (primitive.material.alpha_mode, primitive.material.alpha_cutoff))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            modes,
+            vec![
+                (gltf::material::AlphaMode::Mask, 0.25),
+                (gltf::material::AlphaMode::Blend, 0.5),
+                (gltf::material::AlphaMode::Mask, 0.5),
+            ]
+        );
+        assert!(layout.primitives.iter().all(|primitive| !primitive.material.is_opaque()));
+        assert_eq!(
+            layout
+                .primitives
+                .iter()
+                .map(|primitive| primitive.material.double_sided)
+                .collect::<Vec<_>>(),
+            vec![false, true, false]
+        );
+
+        let (scene, _) = load_test_scene("indexed.gltf");
+        assert!(scene.layout().primitives[0].material.is_opaque());
+    }
 }
@@ -0,0 +1,12 @@
+/// What one morph target moves one vertex by, at full weight. Mirrors
+/// `MorphDelta` in `shaders/morph.slang` (48 bytes, std430-style vec3s).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MorphDelta {
+    pub position: [f32; 3],
+    pub _padding0: [f32; 1],
+    pub normal: [f32; 3],
+    pub _padding1: [f32; 1],
+    pub tangent: [f32; 3],
+    pub _padding2: [f32; 1],
+}
@@ -5,43 +5,165 @@ use crate::{
     vulkan_abstraction::{self},
 };
 
+/// A node's transform relative to its parent, split into the translation,
+/// rotation and scale animation channels target.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Trs {
+    pub translation: na::Vector3<f32>,
+    pub rotation: na::UnitQuaternion<f32>,
+    pub scale: na::Vector3<f32>,
+}
+
+impl Default for Trs {
+    fn default() -> Self {
+        Self {
+            translation: na::Vector3::zeros(),
+            rotation: na::UnitQuaternion::identity(),
+            scale: na::Vector3::repeat(1.0),
+        }
+    }
+}
+
+impl Trs {
+    /// "matrix = translation * rotation * scale", as the glTF spec composes them.
+    pub fn to_matrix(&self) -> na::Matrix4<f32> {
+        na::Matrix4::new_translation(&self.translation)
+            * self.rotation.to_homogeneous()
+            * na::Matrix4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+impl From<gltf::scene::Transform> for Trs {
+    fn from(transform: gltf::scene::Transform) -> Self {
+        let (translation, [x, y, z, w], scale) = transform.decomposed();
+
+        Self {
+            translation: translation.into(),
+            rotation: na::UnitQuaternion::new_normalize(na::Quaternion::new(w, x, y, z)),
+            scale: scale.into(),
+        }
+    }
+}
+
 pub struct Node {
+    /// Index of the node in the glTF document.
+    index: usize,
+    name: Option<String>,
+    /// World transform.
     transform: na::Matrix4<f32>,
+    /// Transform relative to the parent node, as authored.
+    local_transform: na::Matrix4<f32>,
+    local_trs: Trs,
     mesh: Option<vulkan_abstraction::gltf::Mesh>,
+    camera: Option<crate::Camera>,
+    light: Option<vulkan_abstraction::gltf::PunctualLight>,
+    /// Index in the glTF document of the skin deforming `mesh`.
+    skin: Option<usize>,
+    /// Default weights of the morph targets of `mesh`.
+    morph_weights: Vec<f32>,
     children: Option<Vec<vulkan_abstraction::gltf::Node>>,
 }
 
 impl Default for Node {
     fn default() -> Self {
         Self {
+            index: 0,
+            name: None,
             transform: na::Matrix4::identity(),
+            local_transform: na::Matrix4::identity(),
+            local_trs: Trs::default(),
             mesh: None,
+            camera: None,
+            light: None,
+            skin: None,
+            morph_weights: vec![],
             children: None,
         }
     }
 }
 
 impl Node {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        index: usize,
+        name: Option<String>,
         transform: na::Matrix4<f32>,
+        local_transform: na::Matrix4<f32>,
+        local_trs: Trs,
         mesh: Option<vulkan_abstraction::gltf::Mesh>,
+        camera: Option<crate::Camera>,
+        light: Option<vulkan_abstraction::gltf::PunctualLight>,
+        skin: Option<usize>,
+        morph_weights: Vec<f32>,
         children: Option<Vec<Node>>,
     ) -> SrResult<Self> {
         Ok(Self {
+            index,
+            name,
             transform,
+            local_transform,
+            local_trs,
             mesh,
+            camera,
+            light,
+            skin,
+            morph_weights,
             children,
         })
     }
 
+    /// Index of the node in the glTF document.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The node's world transform, in the pose the scene was authored in.
     pub fn transform(&self) -> &na::Matrix4<f32> {
         &self.transform
     }
 
+    /// The node's transform relative to its parent, as authored (a node given
+    /// as a matrix keeps it exactly, shear included).
+    pub fn local_transform(&self) -> &na::Matrix4<f32> {
+        &self.local_transform
+    }
+
+    /// `local_transform` decomposed into translation, rotation and scale.
+    pub fn local_trs(&self) -> &Trs {
+        &self.local_trs
+    }
+
     pub fn mesh(&self) -> &Option<vulkan_abstraction::gltf::Mesh> {
         &self.mesh
     }
 
+    /// The camera attached to this node, already placed with the node's world
+    /// transform.
+    pub fn camera(&self) -> &Option<crate::Camera> {
+        &self.camera
+    }
+
+    /// The punctual light attached to this node, already placed with the
+    /// node's world transform.
+    pub fn light(&self) -> &Option<vulkan_abstraction::gltf::PunctualLight> {
+        &self.light
+    }
+
+    /// Index in the glTF document of the skin deforming the node's mesh.
+    pub fn skin(&self) -> Option<usize> {
+        self.skin
+    }
+
+    /// Default weights of the morph targets of the node's mesh: the node's
+    /// own, else the mesh's. Empty when neither gives any (all weights zero).
+    pub fn morph_weights(&self) -> &[f32] {
+        &self.morph_weights
+    }
+
     pub fn children(&self) -> &Option<Vec<vulkan_abstraction::gltf::Node>> {
         &self.children
     }
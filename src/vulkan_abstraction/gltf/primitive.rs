@@ -1,14 +1,56 @@
 use crate::vulkan_abstraction;
 
-pub type PrimitiveUniqueKey = (usize, usize);
+/// Identifies the vertex data of a primitive, so that primitives sharing it (a
+/// mesh instanced by several nodes, or meshes reusing the same accessors)
+/// share one BLAS. Besides the accessors, the vertices depend on the material:
+/// it picks the set of coordinates of each texture slot and whether tangents
+/// are generated, so those are part of the key too.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PrimitiveUniqueKey {
+    /// Accessor of the `POSITION` attribute.
+    pub positions: usize,
+    /// Accessor of the indices, `None` for non-indexed geometry.
+    pub indices: Option<usize>,
+    /// Topology, as the glTF mode's GL enum: the same accessors make
+    /// different triangles as a list, a strip or a fan.
+    pub mode: u32,
+    /// The `TEXCOORD_n` set each texture slot reads (base color,
+    /// metallic-roughness, normal, occlusion, emissive), after
+    /// `KHR_texture_transform` overrides: the vertices carry one per slot.
+    pub tex_coords: [u32; 5],
+    /// Whether the vertices get generated tangents: the primitive has no
+    /// `TANGENT` but its material a normal texture to orient.
+    pub generated_tangents: bool,
+}
 
+/// Geometry of a unique primitive as a triangle list, still on the CPU: it's
+/// uploaded by `Scene::load_into_gpu`.
+#[derive(Clone, Debug, Default)]
 pub struct PrimitiveData {
-    pub vertex_buffer: vulkan_abstraction::VertexBuffer,
-    pub index_buffer: vulkan_abstraction::IndexBuffer,
+    pub vertices: Vec<vulkan_abstraction::gltf::Vertex>,
+    pub indices: Vec<u32>,
+    /// Joints and weights of each vertex, empty when the primitive has none.
+    pub skin_vertices: Vec<vulkan_abstraction::gltf::SkinVertex>,
+    /// Deltas of the primitive's morph targets, target-major: those of target
+    /// `t` are `morph_deltas[t * vertices.len()..][..vertices.len()]`.
+    pub morph_deltas: Vec<vulkan_abstraction::gltf::MorphDelta>,
 }
 
 pub struct Primitive {
     pub unique_key: PrimitiveUniqueKey,
     pub material: vulkan_abstraction::gltf::Material,
+    pub morph_target_count: usize,
     pub local_emissive_triangles: Vec<[nalgebra::Vector4<f32>; 3]>,
 }
+
+/// A glTF primitive the loader left out, because it isn't made of triangles
+/// (points and lines have no area for rays to hit).
+#[derive(Clone, Debug)]
+pub struct SkippedPrimitive {
+    /// Index of the mesh in the glTF document.
+    pub mesh_index: usize,
+    pub mesh_name: Option<String>,
+    /// Index of the primitive within its mesh.
+    pub primitive_index: usize,
+    pub mode: gltf::mesh::Mode,
+}
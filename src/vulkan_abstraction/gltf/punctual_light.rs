@@ -0,0 +1,45 @@
+/// A `KHR_lights_punctual` light, in world space and in the exact layout the
+/// shaders read (`PunctualLight` in `shaders/rt_types.slang`, 64 bytes, std430).
+///
+/// Punctual lights are sampled next to the emissive triangles by the ReSTIR DI
+/// passes and by NEE; they have no geometry, so camera rays never see them.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PunctualLight {
+    /// World-space position. Unused by directional lights.
+    pub position: [f32; 3],
+    /// One of [`Self::POINT`], [`Self::SPOT`], [`Self::DIRECTIONAL`].
+    pub kind: u32,
+    /// World-space direction the light points to (normalized). Unused by point lights.
+    pub direction: [f32; 3],
+    /// Distance at which the light reaches zero; `0.0` means infinite.
+    pub range: f32,
+    /// Color * intensity: candela for point and spot lights, lux for directional ones.
+    pub intensity: [f32; 3],
+    /// Cosine of the spot cone inner angle (full intensity inside it).
+    pub inner_cone_cos: f32,
+    /// Cosine of the spot cone outer angle (no light outside it).
+    pub outer_cone_cos: f32,
+    pub _padding: [f32; 3],
+}
+
+impl PunctualLight {
+    pub const POINT: u32 = 0;
+    pub const SPOT: u32 = 1;
+    pub const DIRECTIONAL: u32 = 2;
+}
+
+impl Default for PunctualLight {
+    fn default() -> Self {
+        Self {
+            position: [0.0; 3],
+            kind: Self::POINT,
+            direction: [0.0, 0.0, -1.0],
+            range: 0.0,
+            intensity: [0.0; 3],
+            inner_cone_cos: 1.0,
+            outer_cone_cos: 0.0,
+            _padding: [0.0; 3],
+        }
+    }
+}
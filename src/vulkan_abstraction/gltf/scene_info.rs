@@ -0,0 +1,22 @@
+/// A scene of a glTF document, as listed by `Gltf::scenes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SceneInfo {
+    /// Index of the scene in the glTF document.
+    pub index: usize,
+    pub name: Option<String>,
+    /// How many nodes the scene's hierarchy has, children included.
+    pub node_count: usize,
+    /// Whether the document names it as the scene to display at load time
+    /// (or it's the first one, when the document doesn't say).
+    pub is_default: bool,
+}
+
+/// Which scene of a glTF document to load.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SceneSelector {
+    /// The document's default scene: see [`SceneInfo::is_default`].
+    Default,
+    Index(usize),
+    /// The first scene with this name.
+    Name(String),
+}
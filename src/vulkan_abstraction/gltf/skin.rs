@@ -0,0 +1,46 @@
+use nalgebra as na;
+
+/// A glTF skin: the nodes acting as its joints and, for each joint, the
+/// inverse bind matrix that brings the mesh from its bind pose into the
+/// joint's local space.
+#[derive(Clone, Debug)]
+pub struct Skin {
+    pub name: Option<String>,
+    /// Index in the glTF document of the node of each joint.
+    pub joints: Vec<usize>,
+    /// One per joint (identity when the skin doesn't provide them).
+    pub inverse_bind_matrices: Vec<na::Matrix4<f32>>,
+}
+
+impl Skin {
+    /// The skinning matrix of each joint, for a mesh instanced by a node
+    /// whose world transform is `mesh_node_world`, given `joint_world`, the
+    /// world transform of a node from its glTF index.
+    ///
+    /// The spec places a skinned mesh by its joints alone, ignoring its
+    /// node's transform; the instance is still placed with that transform, so
+    /// the matrices bring the vertices back into the node's space.
+    pub fn joint_matrices(
+        &self,
+        mesh_node_world: &na::Matrix4<f32>,
+        joint_world: impl Fn(usize) -> na::Matrix4<f32>,
+    ) -> Vec<na::Matrix4<f32>> {
+        let world_to_node = mesh_node_world.try_inverse().unwrap_or_else(na::Matrix4::identity);
+
+        self.joints
+            .iter()
+            .zip(&self.inverse_bind_matrices)
+            .map(|(&joint, inverse_bind_matrix)| world_to_node * joint_world(joint) * inverse_bind_matrix)
+            .collect()
+    }
+}
+
+/// The `JOINTS_0` / `WEIGHTS_0` attributes of a vertex: the four joints
+/// (indices into the skin's `joints`) that move it, and how much. Mirrors
+/// `SkinVertex` in `shaders/skinning.slang` (32 bytes).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SkinVertex {
+    pub joints: [u32; 4],
+    pub weights: [f32; 4],
+}
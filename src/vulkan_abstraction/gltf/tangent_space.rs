@@ -0,0 +1,147 @@
+//! Normals and tangents for meshes that don't provide them.
+//!
+//! glTF requires flat normals when `NORMAL` is missing (smooth ones can be
+//! asked for instead, see [`NormalGeneration`]), and recommends MikkTSpace
+//! tangents when `TANGENT` is missing. `Vertex` is `repr(packed)`,
+//! so its fields are always copied out and written back, never borrowed.
+
+use nalgebra as na;
+
+use crate::vulkan_abstraction::gltf::Vertex;
+
+/// How the normals of glTF primitives without `NORMAL` are generated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NormalGeneration {
+    /// The face normal of every triangle, as the spec asks (see
+    /// [`generate_flat_normals`]).
+    #[default]
+    Flat,
+    /// The area-weighted normals of the triangles sharing each vertex (see
+    /// [`generate_smooth_normals`]). Non-indexed geometry shares no vertices,
+    /// so it stays flat.
+    Smooth,
+}
+
+fn position(vertex: &Vertex) -> na::Vector3<f32> {
+    let position = vertex.position;
+    na::Vector3::from(position)
+}
+
+fn normal(vertex: &Vertex) -> na::Vector3<f32> {
+    let normal = vertex.normal;
+    na::Vector3::from(normal)
+}
+
+/// Whether a vertex has no usable normal (all zeros, as `Vertex::default()`).
+pub fn is_normal_missing(vertex: &Vertex) -> bool {
+    normal(vertex).norm_squared() < 1e-12
+}
+
+/// Whether a vertex has no usable tangent (all zeros, as `Vertex::default()`).
+pub fn is_tangent_missing(vertex: &Vertex) -> bool {
+    let tangent = vertex.tangent;
+    na::Vector3::new(tangent[0], tangent[1], tangent[2]).norm_squared() < 1e-12
+}
+
+/// Unweld a triangle list so that every triangle has its own three vertices,
+/// each with the triangle's face normal. Returns the new vertices and their
+/// (sequential) indices.
+pub fn generate_flat_normals(vertices: &[Vertex], indices: &[u32]) -> (Vec<Vertex>, Vec<u32>) {
+    let mut flat_vertices = Vec::with_capacity(indices.len());
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = triangle.iter().map(|&i| vertices[i as usize]).collect::<Vec<_>>();
+        let face_normal = (position(&corners[1]) - position(&corners[0])).cross(&(position(&corners[2]) - position(&corners[0])));
+        let face_normal = face_normal.try_normalize(1e-12).unwrap_or_else(na::Vector3::z);
+
+        for mut corner in corners {
+            corner.normal = face_normal.into();
+            flat_vertices.push(corner);
+        }
+    }
+
+    let flat_indices = (0..flat_vertices.len() as u32).collect();
+    (flat_vertices, flat_indices)
+}
+
+/// Give every vertex whose normal is missing the area-weighted average of the
+/// normals of the triangles sharing it. Vertices that already have a normal
+/// are left untouched.
+pub fn generate_smooth_normals(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut accumulated = vec![na::Vector3::<f32>::zeros(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|k| position(&vertices[triangle[k] as usize]));
+        // the cross product's length is twice the area: that is the weighting
+        let face_normal = (b - a).cross(&(c - a));
+        for &i in triangle {
+            accumulated[i as usize] += face_normal;
+        }
+    }
+
+    for (vertex, normal) in vertices.iter_mut().zip(accumulated) {
+        if is_normal_missing(vertex) {
+            vertex.normal = normal.try_normalize(1e-12).unwrap_or_else(na::Vector3::z).into();
+        }
+    }
+}
+
+/// MikkTSpace-style tangents, from the normal texture coordinates: per-corner
+/// UV derivatives weighted by the corner angle, accumulated per vertex,
+/// orthogonalized against the normal, with the bitangent sign in `w`. Unlike
+/// full MikkTSpace, vertices are never split where the tangent frames of
+/// their triangles disagree (e.g. on UV mirroring seams). Vertices that
+/// already have a tangent are left untouched.
+pub fn generate_tangents(vertices: &mut [Vertex], indices: &[u32]) {
+    let mut tangents = vec![na::Vector3::<f32>::zeros(); vertices.len()];
+    let mut bitangents = vec![na::Vector3::<f32>::zeros(); vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let corners = [0, 1, 2].map(|k| vertices[triangle[k] as usize]);
+        let positions = corners.map(|v| position(&v));
+        let uvs = corners.map(|v| {
+            let uv = v.normal_tex_coord;
+            na::Vector2::from(uv)
+        });
+
+        let (edge1, edge2) = (positions[1] - positions[0], positions[2] - positions[0]);
+        let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        if det.abs() < 1e-12 {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+
+        for k in 0..3 {
+            let to_next = positions[(k + 1) % 3] - positions[k];
+            let to_prev = positions[(k + 2) % 3] - positions[k];
+            let angle = match (to_next.try_normalize(1e-12), to_prev.try_normalize(1e-12)) {
+                (Some(next), Some(prev)) => next.dot(&prev).clamp(-1.0, 1.0).acos(),
+                _ => 0.0,
+            };
+            let i = triangle[k] as usize;
+            tangents[i] += tangent * angle;
+            bitangents[i] += bitangent * angle;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        if !is_tangent_missing(vertex) {
+            continue;
+        }
+        let n = normal(vertex).try_normalize(1e-12).unwrap_or_else(na::Vector3::z);
+
+        // Gram-Schmidt; any vector perpendicular to the normal will do when the UVs are degenerate
+        let t = (tangent - n * n.dot(&tangent)).try_normalize(1e-12).unwrap_or_else(|| {
+            n.cross(&na::Vector3::x())
+                .try_normalize(1e-12)
+                .unwrap_or_else(|| n.cross(&na::Vector3::y()).normalize())
+        });
+        // glTF's v axis points down the image while the normal map's +Y points
+        // up, so the bitangent the shader rebuilds (cross(N, T) * w) must follow -dP/dv
+        let w = if n.cross(&t).dot(&-bitangent) < 0.0 { -1.0 } else { 1.0 };
+
+        vertex.tangent = [t.x, t.y, t.z, w];
+    }
+}
@@ -7,8 +7,63 @@ pub struct Sampler {
 
 pub struct Texture {
     pub sampler: Option<usize>,
-    /// The image index
-    ///
-    /// (technically it is not required by the spec)
-    pub source: usize,
+    /// The index of the image the data slots (normal, metallic-roughness,
+    /// occlusion) sample, uploaded in a UNORM format.
+    pub source: Option<usize>,
+    /// The index of the image the color slots (base color, emissive) sample,
+    /// uploaded in an sRGB format. A texture only gets the images its slots
+    /// use: both when it's used both ways.
+    pub srgb_source: Option<usize>,
+}
+
+/// How a material slot's texels are encoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TexelEncoding {
+    /// Colors (base color, emissive): sRGB-encoded, decoded to linear by the
+    /// sampler.
+    Srgb,
+    /// Data (normals, metallic-roughness, occlusion), read as stored.
+    Linear,
+}
+
+/// A `KHR_texture_transform`: the offset, rotation (radians, counterclockwise)
+/// and scale applied to a texture slot's coordinates before sampling.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureTransform {
+    pub offset: [f32; 2],
+    pub rotation: f32,
+    pub scale: [f32; 2],
+}
+
+impl Default for TextureTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            rotation: 0.0,
+            scale: [1.0, 1.0],
+        }
+    }
+}
+
+impl TextureTransform {
+    /// The two rows of the 2x3 matrix `translation * rotation * scale` of the
+    /// extension's spec, each padded to four floats: `uv' = (row0 · (uv, 1),
+    /// row1 · (uv, 1))`.
+    pub fn rows(&self) -> [[f32; 4]; 2] {
+        let (sin, cos) = self.rotation.sin_cos();
+        let [sx, sy] = self.scale;
+        let [ox, oy] = self.offset;
+
+        [[cos * sx, sin * sy, ox, 0.0], [-sin * sx, cos * sy, oy, 0.0]]
+    }
+}
+
+impl From<gltf::texture::TextureTransform<'_>> for TextureTransform {
+    fn from(transform: gltf::texture::TextureTransform<'_>) -> Self {
+        Self {
+            offset: transform.offset(),
+            rotation: transform.rotation(),
+            scale: transform.scale(),
+        }
+    }
 }
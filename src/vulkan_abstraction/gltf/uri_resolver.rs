@@ -0,0 +1,71 @@
+use crate::error::{SrError, SrResult};
+
+/// Supplies the files a glTF document loaded from memory references by URI:
+/// its external buffers and images, wherever they're stored (an archive, the
+/// network...). `data:` URIs and the binary chunk of a .glb are read without
+/// it.
+///
+/// Closures `Fn(&str) -> SrResult<Vec<u8>>` are resolvers.
+pub trait UriResolver {
+    /// The content of the file at `uri`, as written in the document: relative
+    /// references are still relative to the document and percent-encoding is
+    /// not undone.
+    fn resolve(&self, uri: &str) -> SrResult<Vec<u8>>;
+}
+
+impl<F: Fn(&str) -> SrResult<Vec<u8>>> UriResolver for F {
+    fn resolve(&self, uri: &str) -> SrResult<Vec<u8>> {
+        self(uri)
+    }
+}
+
+/// The resolver of self-contained documents (a .glb with everything in its
+/// binary chunk, data URIs): any external reference is an error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct EmbeddedOnly;
+
+impl UriResolver for EmbeddedOnly {
+    fn resolve(&self, uri: &str) -> SrResult<Vec<u8>> {
+        Err(SrError::new_custom(format!(
+            "gltf: the document references {uri:?}, but it was loaded without a resolver for external files"
+        )))
+    }
+}
+
+/// The resolver of documents on disk: URIs are percent-encoded paths relative
+/// to the directory holding the document.
+#[derive(Clone, Debug)]
+pub struct Directory(pub std::path::PathBuf);
+
+impl UriResolver for Directory {
+    fn resolve(&self, uri: &str) -> SrResult<Vec<u8>> {
+        let path = self.0.join(percent_decode(uri));
+        std::fs::read(&path).map_err(|e| SrError::new_custom(format!("gltf: cannot read {}: {e}", path.display())))
+    }
+}
+
+/// `uri` with its `%XX` escapes undone. Malformed escapes are kept as written.
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
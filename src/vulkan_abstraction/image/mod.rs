@@ -1,8 +1,10 @@
 pub mod sampler;
+pub mod streaming;
 pub mod swapchain;
 pub mod texture;
 
 pub use sampler::*;
+pub use streaming::*;
 pub use texture::*;
 
 use ash::vk;
@@ -11,13 +13,32 @@ use std::rc::Rc;
 
 use std::sync::Arc;
 
+use crate::error::{SrError, SrResult};
 use crate::render_graph::resource::RgImportable;
 use crate::render_graph::resource::{AnyRenderResource, GraphResourceImportInfo, Resource, ResourceDesc};
 use crate::vulkan_abstraction::Buffer;
 use crate::vulkan_abstraction::descriptor_heap::{DescriptorSlot, ResourceDescriptorKind};
-use crate::{error::SrResult, utils, vulkan_abstraction};
+use crate::{utils, vulkan_abstraction};
 use vk_sync_fork as vk_sync;
 
+/// The copy filling every mip level of a texture from a staging buffer, made
+/// by [`Image::new_texture_with_upload`]. The image starts out `UNDEFINED`
+/// and is left in `SHADER_READ_ONLY_OPTIMAL`.
+#[derive(Clone, Debug)]
+pub struct TextureCopy {
+    pub staging_buffer: vk::Buffer,
+    pub image: vk::Image,
+    pub regions: Vec<vk::BufferImageCopy>,
+    pub mip_levels: u32,
+}
+
+/// A [`TextureCopy`] with the staging buffer it reads, to keep alive until the
+/// copy has run.
+pub struct TextureUpload {
+    pub staging_buffer: vulkan_abstraction::StagingBuffer<u8>,
+    pub copy: TextureCopy,
+}
+
 pub struct Image {
     core: Rc<vulkan_abstraction::Core>,
     image: vk::Image,
@@ -91,24 +112,81 @@ impl Image {
     ) -> SrResult<Self> {
         let usage_flags = vk::ImageUsageFlags::TRANSFER_DST | usage_flags;
 
+        let (image_data, image_format) = Self::expand_to_sampled_format(image_data, format);
+        let mut image = Self::new(core, extent, image_format, tiling, location, usage_flags, name)?;
+
+        let staging_buffer = vulkan_abstraction::StagingBuffer::new_temp_from_data(Rc::clone(&image.core), &image_data)?;
+
+        image.copy_from_buffer(&staging_buffer)?;
+
+        Ok(image)
+    }
+
+    /// `image_data` in `format`, converted to the format of the image it's uploaded to.
+    fn expand_to_sampled_format(image_data: Vec<u8>, format: vk::Format) -> (Vec<u8>, vk::Format) {
         // format is the format of the data. we don't even try to check if it's supported by the gpu since
         // in general only RGBA8 is supported. TODO: it would be better to do so, and also we're assuming UNORM for no reason
-        let mut image = Self::new(core, extent, vk::Format::R8G8B8A8_UNORM, tiling, location, usage_flags, name)?;
+        // RGBA32F (HDR environment maps) is the exception: it's kept as is, every gpu can sample it
+        let image_format = match format {
+            vk::Format::R32G32B32A32_SFLOAT => format,
+            _ => vk::Format::R8G8B8A8_UNORM,
+        };
 
         let image_data = match format {
-            vk::Format::R8G8B8A8_UNORM => image_data,
+            vk::Format::R8G8B8A8_UNORM | vk::Format::R32G32B32A32_SFLOAT => image_data,
             vk::Format::R8G8B8_UNORM => utils::realign_data(&image_data, 3, 4),
             vk::Format::R8G8_UNORM => utils::realign_data(&image_data, 2, 4),
             vk::Format::R8_UNORM => utils::realign_data(&image_data, 1, 4),
             _ => todo!(), // TODO
         };
 
-        let staging_buffer = vulkan_abstraction::StagingBuffer::new_temp_from_data(Rc::clone(&image.core), &image_data)?;
+        (image_data, image_format)
+    }
 
-        image.copy_from_buffer(&staging_buffer)?;
+    /// Create a texture the shaders sample. `levels` are the tightly packed
+    /// texels of its mip levels in `format`, largest first: the image has
+    /// exactly these levels. Returns it with the upload filling it, for a
+    /// command buffer of the graphics queue to record before anything samples
+    /// the image (see `RenderGraph::add_prologue_texture_copies`).
+    pub fn new_texture_with_upload(
+        core: Rc<vulkan_abstraction::Core>,
+        levels: &[Vec<u8>],
+        extent: vk::Extent3D,
+        format: vk::Format,
+        name: &'static str,
+    ) -> SrResult<(Self, TextureUpload)> {
+        let format_features = unsafe {
+            core.instance()
+                .get_physical_device_format_properties(core.device().physical_device(), format)
+        }
+        .optimal_tiling_features;
+        if !format_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE) {
+            return Err(SrError::new_custom(format!(
+                "{name}: {format:?} images cannot be sampled on this device"
+            )));
+        }
 
-        Ok(image)
+        let image = Self::new_with_mip_levels(
+            core,
+            extent,
+            format,
+            vk::ImageTiling::OPTIMAL,
+            gpu_allocator::MemoryLocation::GpuOnly,
+            vk::ImageUsageFlags::SAMPLED | vk::ImageUsageFlags::TRANSFER_DST,
+            levels.len() as u32,
+            name,
+        )?;
+        let staging_buffer = vulkan_abstraction::StagingBuffer::new_temp_from_data(Rc::clone(&image.core), &levels.concat())?;
+        let copy = TextureCopy {
+            staging_buffer: staging_buffer.inner(),
+            image: image.image,
+            regions: level_copy_regions(levels, extent),
+            mip_levels: levels.len() as u32,
+        };
+
+        Ok((image, TextureUpload { staging_buffer, copy }))
     }
+
     /// Construct an image from a render-graph descriptor. Equivalent to calling
     /// `Image::new` with the desc's fields; kept as a separate entry point so the
     /// graph can build images straight from a `&ImageDesc` without unpacking.
@@ -132,6 +210,20 @@ impl Image {
         location: gpu_allocator::MemoryLocation,
         usage_flags: vk::ImageUsageFlags,
         name: &'static str,
+    ) -> SrResult<Self> {
+        Self::new_with_mip_levels(core, extent, format, tiling, location, usage_flags, 1, name)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_with_mip_levels(
+        core: Rc<vulkan_abstraction::Core>,
+        extent: vk::Extent3D,
+        format: vk::Format,
+        tiling: vk::ImageTiling,
+        location: gpu_allocator::MemoryLocation,
+        usage_flags: vk::ImageUsageFlags,
+        mip_levels: u32,
+        name: &'static str,
     ) -> SrResult<Self> {
         let image = {
             let image_create_info = vk::ImageCreateInfo::default()
@@ -139,7 +231,7 @@ impl Image {
                 .format(format)
                 .extent(extent)
                 .flags(vk::ImageCreateFlags::empty())
-                .mip_levels(1)
+                .mip_levels(mip_levels)
                 .array_layers(1)
                 .samples(vk::SampleCountFlags::TYPE_1)
                 .tiling(tiling)
@@ -175,7 +267,7 @@ impl Image {
         let image_subresource_range = vk::ImageSubresourceRange {
             aspect_mask: vk::ImageAspectFlags::COLOR,
             base_mip_level: 0,
-            level_count: 1,
+            level_count: mip_levels,
             base_array_layer: 0,
             layer_count: 1,
         };
@@ -402,19 +494,34 @@ impl Image {
         slot.shader_index()
     }
 
+    /// Write a `SAMPLED_IMAGE` descriptor of the image into `slot`, a heap slot
+    /// the caller allocated and frees. Unlike [`Self::sampled_slot`]'s, it
+    /// outlives the image: another image can be written into it later.
+    pub fn write_sampled_descriptor(&self, slot: DescriptorSlot) -> SrResult<()> {
+        self.core.descriptor_heap_mut().write_image(
+            slot,
+            &self.view_info(),
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ResourceDescriptorKind::SampledImage,
+        )
+    }
+
     fn write_image_slot(&self, kind: ResourceDescriptorKind, layout: vk::ImageLayout) -> DescriptorSlot {
-        let view_info = vk::ImageViewCreateInfo::default()
-            .view_type(self.view_type)
-            .format(self.format)
-            .subresource_range(self.image_subresource_range)
-            .image(self.image);
         let mut heap = self.core.descriptor_heap_mut();
         let slot = heap.alloc_resource_slot(kind);
-        heap.write_image(slot, &view_info, layout, kind)
+        heap.write_image(slot, &self.view_info(), layout, kind)
             .expect("descriptor heap write_image failed");
         slot
     }
 
+    fn view_info(&self) -> vk::ImageViewCreateInfo<'static> {
+        vk::ImageViewCreateInfo::default()
+            .view_type(self.view_type)
+            .format(self.format)
+            .subresource_range(self.image_subresource_range)
+            .image(self.image)
+    }
+
     pub fn byte_size(&self) -> u64 {
         self.byte_size
     }
@@ -483,3 +590,34 @@ pub struct ImageDesc {
 impl ResourceDesc for ImageDesc {
     type Resource = Image;
 }
+
+/// One copy region per level of the tightly packed mip chain `levels`, largest
+/// first, of a texture whose base level is `extent`.
+fn level_copy_regions(levels: &[Vec<u8>], extent: vk::Extent3D) -> Vec<vk::BufferImageCopy> {
+    let mut buffer_offset = 0;
+    levels
+        .iter()
+        .enumerate()
+        .map(|(level, data)| {
+            let region = vk::BufferImageCopy::default()
+                .buffer_offset(buffer_offset)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(
+                    vk::ImageSubresourceLayers::default()
+                        .aspect_mask(vk::ImageAspectFlags::COLOR)
+                        .mip_level(level as u32)
+                        .base_array_layer(0)
+                        .layer_count(1),
+                )
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width: (extent.width >> level).max(1),
+                    height: (extent.height >> level).max(1),
+                    depth: 1,
+                });
+            buffer_offset += data.len() as vk::DeviceSize;
+            region
+        })
+        .collect()
+}
@@ -18,6 +18,7 @@ struct SamplerParams {
     address_mode_v: vk::SamplerAddressMode,
     address_mode_w: vk::SamplerAddressMode,
     mipmap_mode: vk::SamplerMipmapMode,
+    mipmapped: bool,
     max_anisotropy: f32,
 }
 
@@ -38,9 +39,11 @@ impl Sampler {
             desc.address_mode_v,
             desc.address_mode_w,
             desc.mipmap_mode,
+            desc.mipmapped,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         core: Rc<vulkan_abstraction::Core>,
         min_filter: vk::Filter,
@@ -49,6 +52,7 @@ impl Sampler {
         address_mode_v: vk::SamplerAddressMode,
         address_mode_w: vk::SamplerAddressMode,
         mipmap_mode: vk::SamplerMipmapMode,
+        mipmapped: bool,
     ) -> SrResult<Self> {
         let params = SamplerParams {
             min_filter,
@@ -57,6 +61,7 @@ impl Sampler {
             address_mode_v,
             address_mode_w,
             mipmap_mode,
+            mipmapped,
             max_anisotropy: core.device().properties().limits.max_sampler_anisotropy,
         };
         let slot = core.descriptor_heap_mut().alloc_sampler_slot();
@@ -90,7 +95,7 @@ impl SamplerParams {
             .mipmap_mode(self.mipmap_mode)
             .mip_lod_bias(0.0)
             .min_lod(0.0)
-            .max_lod(0.0)
+            .max_lod(if self.mipmapped { vk::LOD_CLAMP_NONE } else { 0.0 })
     }
 }
 
@@ -121,6 +126,7 @@ impl crate::render_graph::graph::RgImportable<SamplerDesc> for std::sync::Arc<Sa
             address_mode_v: self.params.address_mode_v,
             address_mode_w: self.params.address_mode_w,
             mipmap_mode: self.params.mipmap_mode,
+            mipmapped: self.params.mipmapped,
         }
     }
 }
@@ -139,6 +145,9 @@ pub struct SamplerDesc {
     pub address_mode_v: vk::SamplerAddressMode,
     pub address_mode_w: vk::SamplerAddressMode,
     pub mipmap_mode: vk::SamplerMipmapMode,
+    /// Whether the mip levels below the base one are read. glTF's `NEAREST`
+    /// and `LINEAR` minification filters only read the base level.
+    pub mipmapped: bool,
 }
 
 impl ResourceDesc for SamplerDesc {
@@ -0,0 +1,389 @@
+use std::cmp::Reverse;
+use std::rc::Rc;
+
+use ash::vk;
+
+use crate::error::SrResult;
+use crate::vulkan_abstraction;
+use crate::vulkan_abstraction::descriptor_heap::{DescriptorSlot, ResourceDescriptorKind};
+use crate::vulkan_abstraction::{Buffer, HostAccessibleBuffer};
+
+/// Mip levels at most this many texels wide and tall are uploaded with their
+/// texture and stay resident: shaders sample them until feedback asks for
+/// more.
+pub const RESIDENT_TAIL_EXTENT: u32 = 64;
+
+/// How much GPU memory the streamed textures may take until
+/// [`crate::Renderer::set_texture_budget`] says otherwise.
+pub const DEFAULT_TEXTURE_BUDGET: u64 = 1024 * 1024 * 1024;
+
+/// About how many bytes of mip levels [`TextureStreaming::update`] uploads per
+/// frame.
+pub const TEXTURE_STREAMING_UPLOAD_BUDGET: u64 = 16 * 1024 * 1024;
+
+/// A feedback entry no hit wrote to.
+const NO_REQUEST: i32 = i32::MAX;
+
+/// A texture whose larger mip levels are uploaded on demand. Every level stays
+/// in CPU memory; the GPU holds the tail of the chain from
+/// [`Self::first_resident_level`] on, in an image shaders reach through a heap
+/// slot of the texture's own. Changing the resident levels moves the texture
+/// to a new image and slot, leaving the old ones to the frames in flight:
+/// materials refer to the texture through an indirection table (see
+/// `ResourceManager::stream_textures`), which follows it.
+pub struct StreamedTexture {
+    core: Rc<vulkan_abstraction::Core>,
+    /// Every mip level, largest first.
+    levels: Vec<Vec<u8>>,
+    extent: vk::Extent3D,
+    format: vk::Format,
+    name: &'static str,
+    slot: DescriptorSlot,
+    /// Levels `first_resident..` of the chain.
+    resident: vulkan_abstraction::Image,
+    first_resident: u32,
+    /// The first level of the tail that stays resident.
+    tail_level: u32,
+    /// The finest level the hit shaders last asked for.
+    requested_level: u32,
+    /// The frame whose hits last asked for `requested_level`.
+    requested_frame: u64,
+    /// The first frame sampling `resident`. Feedback is relative to the
+    /// resident levels: earlier frames' is stale.
+    resident_since: u64,
+}
+
+impl StreamedTexture {
+    /// Create a texture from the mip chain `levels` (largest first) of an
+    /// image whose base level is `extent`, with the tail of the chain
+    /// resident, or all of it with `full_resolution`: a texture sampled
+    /// without mip maps only ever reads its base level. Returns it with the
+    /// upload of the resident levels, to record before anything samples it
+    /// (see [`vulkan_abstraction::Image::new_texture_with_upload`]).
+    pub fn new(
+        core: Rc<vulkan_abstraction::Core>,
+        levels: Vec<Vec<u8>>,
+        extent: vk::Extent3D,
+        format: vk::Format,
+        full_resolution: bool,
+        name: &'static str,
+    ) -> SrResult<(Self, vulkan_abstraction::TextureUpload)> {
+        let last_level = levels.len().saturating_sub(1) as u32;
+        let tail_level = if full_resolution {
+            0
+        } else {
+            (0..last_level)
+                .find(|&level| {
+                    let level_extent = level_extent(extent, level);
+                    level_extent.width.max(level_extent.height) <= RESIDENT_TAIL_EXTENT
+                })
+                .unwrap_or(last_level)
+        };
+
+        let (resident, upload) = vulkan_abstraction::Image::new_texture_with_upload(
+            Rc::clone(&core),
+            &levels[tail_level as usize..],
+            level_extent(extent, tail_level),
+            format,
+            name,
+        )?;
+        let slot = core
+            .descriptor_heap_mut()
+            .alloc_resource_slot(ResourceDescriptorKind::SampledImage);
+        let texture = Self {
+            resident_since: next_frame(&core),
+            core,
+            levels,
+            extent,
+            format,
+            name,
+            slot,
+            resident,
+            first_resident: tail_level,
+            tail_level,
+            requested_level: tail_level,
+            requested_frame: 0,
+        };
+        texture.resident.write_sampled_descriptor(texture.slot)?;
+
+        Ok((texture, upload))
+    }
+
+    /// The heap slot shaders sample the resident levels through. It changes
+    /// with them.
+    pub fn slot(&self) -> u32 {
+        self.slot.shader_index()
+    }
+
+    pub fn level_count(&self) -> u32 {
+        self.levels.len() as u32
+    }
+
+    /// The largest mip level on the GPU.
+    pub fn first_resident_level(&self) -> u32 {
+        self.first_resident
+    }
+
+    /// The size of the texels of the resident levels.
+    pub fn resident_bytes(&self) -> u64 {
+        self.bytes_from(self.first_resident)
+    }
+
+    fn bytes_from(&self, level: u32) -> u64 {
+        self.levels[level as usize..].iter().map(|level| level.len() as u64).sum()
+    }
+
+    /// Fold in what the hits of `frame` wrote to the texture's feedback entry.
+    fn record_request(&mut self, request: i32, frame: u64) {
+        if request == NO_REQUEST || frame < self.resident_since {
+            return;
+        }
+        let level = self.first_resident as i64 + request as i64;
+        self.requested_level = level.clamp(0, self.levels.len() as i64 - 1) as u32;
+        self.requested_frame = frame;
+    }
+
+    /// The level to evict the texture down to, to make room for a texture
+    /// asked for in `frame`: the tail when this one was asked for less
+    /// recently, else its levels finer than it asked for. `None` when that
+    /// evicts nothing.
+    fn eviction_level(&self, frame: u64) -> Option<u32> {
+        let level = if self.requested_frame < frame {
+            self.tail_level
+        } else {
+            self.requested_level.min(self.tail_level)
+        };
+        (level > self.first_resident).then_some(level)
+    }
+
+    /// Make levels `first_level..` resident (the tail at least), in a new image
+    /// behind a new slot: the slot frames in flight sample is left alone.
+    /// Records in `changes` the image and slot that were resident, and the
+    /// upload filling the new image, for the upcoming frame to record.
+    fn make_resident(&mut self, first_level: u32, changes: &mut StreamingChanges) -> SrResult<()> {
+        let first_level = first_level.min(self.tail_level);
+        let (image, upload) = vulkan_abstraction::Image::new_texture_with_upload(
+            Rc::clone(&self.core),
+            &self.levels[first_level as usize..],
+            level_extent(self.extent, first_level),
+            self.format,
+            self.name,
+        )?;
+        let slot = self
+            .core
+            .descriptor_heap_mut()
+            .alloc_resource_slot(ResourceDescriptorKind::SampledImage);
+        image.write_sampled_descriptor(slot)?;
+
+        self.first_resident = first_level;
+        self.resident_since = next_frame(&self.core);
+        changes.retired.push(RetiredLevels {
+            core: Rc::clone(&self.core),
+            slot: std::mem::replace(&mut self.slot, slot),
+            _image: std::mem::replace(&mut self.resident, image),
+        });
+        changes.uploads.push(upload);
+        Ok(())
+    }
+}
+
+impl Drop for StreamedTexture {
+    fn drop(&mut self) {
+        self.core.descriptor_heap_mut().free(self.slot);
+    }
+}
+
+/// The image and heap slot of levels of a [`StreamedTexture`] that stopped
+/// being resident, which frames in flight may still sample. Dropping it frees
+/// both.
+pub struct RetiredLevels {
+    core: Rc<vulkan_abstraction::Core>,
+    slot: DescriptorSlot,
+    _image: vulkan_abstraction::Image,
+}
+
+impl Drop for RetiredLevels {
+    fn drop(&mut self) {
+        self.core.descriptor_heap_mut().free(self.slot);
+    }
+}
+
+/// What a [`TextureStreaming::update`] changed: the levels that stopped being
+/// resident, and the uploads of the images that replaced them, which the
+/// upcoming frame records ahead of its passes.
+#[derive(Default)]
+pub struct StreamingChanges {
+    pub retired: Vec<RetiredLevels>,
+    pub uploads: Vec<vulkan_abstraction::TextureUpload>,
+}
+
+fn level_extent(extent: vk::Extent3D, level: u32) -> vk::Extent3D {
+    vk::Extent3D {
+        width: (extent.width >> level).max(1),
+        height: (extent.height >> level).max(1),
+        depth: 1,
+    }
+}
+
+/// Absolute number of the next frame to be rendered.
+fn next_frame(core: &vulkan_abstraction::Core) -> u64 {
+    *core.absolute_frame_count.borrow() as u64 + 1
+}
+
+/// Streams the mip levels of [`StreamedTexture`]s on the feedback of the hit
+/// shaders, which record per image slot the finest level a hit wanted,
+/// relative to the slot's resident levels (see `request_texture_lod` in
+/// `rt_utils.slang`). The textures asked for most recently get their levels
+/// first; the ones asked for least recently lose theirs when the budget runs
+/// out.
+pub struct TextureStreaming {
+    /// One feedback buffer per frame in flight, indexed by the frame's slot
+    /// like the renderer's camera matrices: when a slot comes around again,
+    /// the frame that wrote its buffer is done, and it's read back and
+    /// cleared for the next. Indexed by image heap slot (the image section
+    /// starts the heap), host-visible so the CPU reads it in place. Sized to
+    /// the heap's image section, and reallocated when the section outgrows it.
+    feedback: Vec<vulkan_abstraction::StagingBuffer<i32>>,
+    budget: u64,
+    core: Rc<vulkan_abstraction::Core>,
+}
+
+impl TextureStreaming {
+    pub fn new(core: Rc<vulkan_abstraction::Core>) -> SrResult<Self> {
+        let feedback = (0..crate::MAX_FRAMES_IN_FLIGHT)
+            .map(|_| new_feedback_buffer(&core))
+            .collect::<SrResult<Vec<_>>>()?;
+
+        Ok(Self {
+            feedback,
+            budget: DEFAULT_TEXTURE_BUDGET,
+            core,
+        })
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    /// Cap the bytes of resident levels, from the next [`Self::update`] on.
+    pub fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+    }
+
+    /// Heap slot of the feedback buffer the hit shaders of `frame` write to.
+    pub fn feedback_slot(&self, frame: u64) -> u32 {
+        self.feedback[frame as usize % crate::MAX_FRAMES_IN_FLIGHT]
+            .raw()
+            .storage_slot()
+    }
+
+    /// Fold the feedback of the last frame rendered in `upcoming_frame`'s slot
+    /// into `textures` and clear it, evict levels until the budget is met,
+    /// then upload about [`TEXTURE_STREAMING_UPLOAD_BUDGET`] bytes of the
+    /// levels asked for. Every texture whose resident levels changed is behind
+    /// a new [`StreamedTexture::slot`].
+    pub fn update(&mut self, textures: &mut [&mut StreamedTexture], upcoming_frame: u64) -> SrResult<StreamingChanges> {
+        let feedback_frame = upcoming_frame.saturating_sub(crate::MAX_FRAMES_IN_FLIGHT as u64);
+        let feedback = &mut self.feedback[upcoming_frame as usize % crate::MAX_FRAMES_IN_FLIGHT];
+        {
+            let requests = feedback.map()?;
+            for texture in textures.iter_mut() {
+                if let Some(&request) = requests.get(texture.slot() as usize) {
+                    texture.record_request(request, feedback_frame);
+                }
+            }
+        }
+        if feedback.len() < image_capacity(&self.core) {
+            // the heap grew: the hits may write past the end of the buffer's entries
+            *feedback = new_feedback_buffer(&self.core)?;
+        } else {
+            feedback.map_mut()?.fill(NO_REQUEST);
+        }
+
+        let mut changes = StreamingChanges::default();
+        let mut resident_bytes = textures.iter().map(|texture| texture.resident_bytes()).sum();
+
+        // a lowered budget keeps what the last feedback asked for, if it can
+        self.make_room(textures, &mut resident_bytes, 0, feedback_frame, None, &mut changes)?;
+
+        // the textures asked for most recently first, then the ones missing the most levels
+        let mut wanting: Vec<usize> = (0..textures.len())
+            .filter(|&i| textures[i].requested_level < textures[i].first_resident)
+            .collect();
+        wanting.sort_by_key(|&i| {
+            (
+                Reverse(textures[i].requested_frame),
+                Reverse(textures[i].first_resident - textures[i].requested_level),
+            )
+        });
+
+        let mut uploaded_bytes = 0;
+        for i in wanting {
+            if uploaded_bytes >= TEXTURE_STREAMING_UPLOAD_BUDGET {
+                break;
+            }
+            let texture = &textures[i];
+            let (level, frame) = (texture.requested_level, texture.requested_frame);
+            // a texture evicted to make room for an earlier one no longer misses the same levels
+            let Some(needed) = texture.bytes_from(level).checked_sub(texture.resident_bytes()) else {
+                continue;
+            };
+            if needed == 0 || !self.make_room(textures, &mut resident_bytes, needed, frame, Some(i), &mut changes)? {
+                continue;
+            }
+
+            textures[i].make_resident(level, &mut changes)?;
+            resident_bytes += needed;
+            uploaded_bytes += needed;
+        }
+
+        Ok(changes)
+    }
+
+    /// Evict levels until `needed` more bytes fit the budget, from the
+    /// textures asked for less recently than `frame` (least recently first)
+    /// or finer than asked for, `except` one. Returns whether they fit.
+    fn make_room(
+        &self,
+        textures: &mut [&mut StreamedTexture],
+        resident_bytes: &mut u64,
+        needed: u64,
+        frame: u64,
+        except: Option<usize>,
+        changes: &mut StreamingChanges,
+    ) -> SrResult<bool> {
+        while *resident_bytes + needed > self.budget {
+            let victim = textures
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| Some(i) != except)
+                .filter_map(|(i, texture)| Some((i, texture.requested_frame, texture.eviction_level(frame)?)))
+                .min_by_key(|&(_, requested_frame, _)| requested_frame);
+            let Some((victim, _, level)) = victim else {
+                return Ok(false);
+            };
+
+            let texture = &mut textures[victim];
+            let before = texture.resident_bytes();
+            texture.make_resident(level, changes)?;
+            *resident_bytes = *resident_bytes - before + texture.resident_bytes();
+        }
+        Ok(true)
+    }
+}
+
+/// How many image slots the descriptor heap has: one feedback entry each.
+fn image_capacity(core: &vulkan_abstraction::Core) -> usize {
+    core.descriptor_heap()
+        .resource_section_capacity(vulkan_abstraction::descriptor_heap::ResourceSection::Image) as usize
+}
+
+/// A cleared feedback buffer with an entry per image slot of the heap.
+fn new_feedback_buffer(core: &Rc<vulkan_abstraction::Core>) -> SrResult<vulkan_abstraction::StagingBuffer<i32>> {
+    vulkan_abstraction::StagingBuffer::new_from_data(
+        Rc::clone(core),
+        &vec![NO_REQUEST; image_capacity(core)],
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        "texture streaming feedback",
+    )
+}
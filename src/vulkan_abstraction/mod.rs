@@ -6,7 +6,9 @@ pub mod descriptor_heap;
 pub mod diagnostics;
 pub mod gltf;
 pub mod image;
+pub mod morphing;
 pub mod resource_manager;
+pub mod skinning;
 pub mod synchronization;
 
 pub mod pipelines;
@@ -23,8 +25,10 @@ pub use pipelines::graphics_pipeline::*;
 pub use core::queue::*;
 pub use diagnostics::*;
 pub use image::*;
+pub use morphing::*;
 pub(crate) use pipelines::ray_tracing_pipeline::*;
 pub(crate) use pipelines::shader_binding_table::*;
 pub(crate) use resource_manager::*;
 pub use resources::*;
+pub use skinning::*;
 pub use synchronization::*;
@@ -0,0 +1,148 @@
+use std::rc::Rc;
+
+use ash::vk;
+
+use crate::error::{SrError, SrResult};
+use crate::render_graph::graph::RenderGraph;
+use crate::render_graph::pass_builder::{ComputeRenderPassBuilder, ComputeShaders, PassCommonDataBuilder, ShaderSource};
+use crate::render_graph::resource::Handle;
+use crate::vulkan_abstraction::{
+    self, AccelerationStructure, Buffer, GpuOnlyBuffer, HostAccessibleBuffer, MorphPushConstant, StagingBuffer,
+};
+use vk_sync_fork as vk_sync;
+
+/// Threads per workgroup of `shaders/morph.slang`.
+const MORPH_WORKGROUP_SIZE: u32 = 64;
+
+/// What the morph target pass needs to blend one morphed BLAS: its base
+/// vertices, the deltas of its targets and the weights to blend them with.
+///
+/// The weights live in one host-visible buffer per frame slot, like the
+/// skinning's joint transforms.
+pub struct MorphedGeometry {
+    base_vertices: GpuOnlyBuffer,
+    deltas: GpuOnlyBuffer,
+    vertex_count: u32,
+    target_count: usize,
+    weights_pool: Vec<StagingBuffer<f32>>,
+    /// Set by [`Self::set_weights`], consumed by the next frame that blends
+    /// this geometry.
+    pending_weights: Option<Vec<f32>>,
+}
+
+impl MorphedGeometry {
+    /// Create the buffers of a mesh's base vertices and its targets' deltas
+    /// (target-major: `deltas[t * vertex_count + i]` moves vertex `i` for
+    /// target `t`). Weights other than zero start out pending: the first frame
+    /// the mesh is rendered in blends them. Returns them with the uploads
+    /// filling the buffers, to record before that frame's passes (see
+    /// [`GpuOnlyBuffer::new_with_upload`]).
+    pub fn new(
+        core: Rc<vulkan_abstraction::Core>,
+        base_vertices: &[vulkan_abstraction::gltf::Vertex],
+        deltas: &[vulkan_abstraction::gltf::MorphDelta],
+        weights: &[f32],
+    ) -> SrResult<(Self, Vec<vulkan_abstraction::BufferUpload>)> {
+        if weights.is_empty() || deltas.len() != base_vertices.len() * weights.len() {
+            return Err(SrError::new_custom(format!(
+                "morph targets: {} deltas for {} vertices and {} targets",
+                deltas.len(),
+                base_vertices.len(),
+                weights.len()
+            )));
+        }
+
+        let usage = vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+        let (base_vertices_buffer, base_vertices_upload) =
+            GpuOnlyBuffer::new_with_upload(Rc::clone(&core), base_vertices, usage, "morph target base vertices")?;
+        let (deltas_buffer, deltas_upload) =
+            GpuOnlyBuffer::new_with_upload(Rc::clone(&core), deltas, usage, "morph target deltas")?;
+        let weights_pool = (0..crate::MAX_FRAMES_IN_FLIGHT)
+            .map(|_| StagingBuffer::new(Rc::clone(&core), weights.len() as u64, usage, "morph target weights"))
+            .collect::<SrResult<Vec<_>>>()?;
+
+        let mut morphed = Self {
+            base_vertices: base_vertices_buffer,
+            deltas: deltas_buffer,
+            vertex_count: base_vertices.len() as u32,
+            target_count: weights.len(),
+            weights_pool,
+            pending_weights: None,
+        };
+        // all-zero weights leave the base vertices the BLAS is built from as they are
+        if weights.iter().any(|&weight| weight != 0.0) {
+            morphed.set_weights(weights)?;
+        }
+        Ok((morphed, base_vertices_upload.into_iter().chain(deltas_upload).collect()))
+    }
+
+    pub fn target_count(&self) -> usize {
+        self.target_count
+    }
+
+    /// Blend the targets with `weights`, one per target, for the next frame.
+    /// Setting several weight sets before a frame picks them up keeps the last
+    /// one.
+    pub fn set_weights(&mut self, weights: &[f32]) -> SrResult<()> {
+        if weights.len() != self.target_count {
+            return Err(SrError::new_custom(format!(
+                "morph targets: got {} weights for a mesh with {} targets",
+                weights.len(),
+                self.target_count
+            )));
+        }
+
+        self.pending_weights = Some(weights.to_vec());
+        Ok(())
+    }
+
+    /// If weights are pending, write them into `frame_slot`'s weight buffer
+    /// and return the push constant of the pass blending into
+    /// `output_vertices`.
+    pub(crate) fn take_pending_weights(
+        &mut self,
+        frame_slot: usize,
+        output_vertices: vk::DeviceAddress,
+    ) -> SrResult<Option<MorphPushConstant>> {
+        let Some(weights) = self.pending_weights.take() else {
+            return Ok(None);
+        };
+
+        let weights_buffer = &mut self.weights_pool[frame_slot];
+        weights_buffer.map_mut()?.copy_from_slice(&weights);
+
+        Ok(Some(MorphPushConstant {
+            base_vertices: self.base_vertices.get_device_address(),
+            deltas: self.deltas.get_device_address(),
+            weights: weights_buffer.get_device_address(),
+            output_vertices,
+            vertex_count: self.vertex_count,
+            target_count: self.target_count as u32,
+        }))
+    }
+}
+
+/// Morph target graph node (heap + Slang): blends the targets of a morphed
+/// BLAS into its vertex buffer (or its skinning rest pose), ahead of the BLAS's
+/// refit.
+///
+/// Like the skinning pass, it declares its write on the BLAS it feeds, so the
+/// graph orders it before the passes that read the blended vertices.
+pub(crate) fn add_morph_pass(
+    rg: &mut RenderGraph,
+    spirv: &[u8],
+    blas_h: &Handle<AccelerationStructure>,
+    push_constant: MorphPushConstant,
+) -> SrResult<()> {
+    let mut common = PassCommonDataBuilder::new(rg, "morph_targets");
+    common.write(blas_h, vk_sync::AccessType::ComputeShaderWrite)?;
+
+    let group_count = push_constant.vertex_count.div_ceil(MORPH_WORKGROUP_SIZE);
+    let pass = ComputeRenderPassBuilder::default()
+        .common(common.build())
+        .shaders(ComputeShaders::new(vec![ShaderSource::Spirv(spirv.to_vec())], 0, "main"))
+        .generate_render(rg, [group_count, 1, 1], move |_| Ok(push_constant))
+        .map_err(|e| SrError::new_custom(format!("morph target pass builder failed: {e}")))?;
+    rg.add_render_pass(pass);
+    Ok(())
+}
@@ -125,6 +125,37 @@ pub struct PostprocessPushConstant {
     pub _output_pad: u32,
     pub exposure: f32,
 }
+/// Push constant for the skinning pass: buffer device addresses of the rest
+/// pose (`Vertex`), the joints and weights (`SkinVertex`), the joint
+/// transforms (row-major 3x4, like `vk::TransformMatrixKHR`) and the BLAS
+/// vertex buffer written with the skinned vertices.
+#[allow(dead_code)] // read by the gpu
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct SkinningPushConstant {
+    pub rest_vertices: vk::DeviceAddress,
+    pub skin_vertices: vk::DeviceAddress,
+    pub joint_transforms: vk::DeviceAddress,
+    pub output_vertices: vk::DeviceAddress,
+    pub vertex_count: u32,
+    pub _pad: u32,
+}
+
+/// Push constant for the morph target pass: buffer device addresses of the
+/// base vertices (`Vertex`), the target-major deltas (`MorphDelta`), one
+/// weight per target and the vertices written with the blended result.
+#[allow(dead_code)] // read by the gpu
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct MorphPushConstant {
+    pub base_vertices: vk::DeviceAddress,
+    pub deltas: vk::DeviceAddress,
+    pub weights: vk::DeviceAddress,
+    pub output_vertices: vk::DeviceAddress,
+    pub vertex_count: u32,
+    pub target_count: u32,
+}
+
 pub struct ComputePipeline<PushConstType> {
     device: Rc<Device>,
     pipeline: vk::Pipeline,
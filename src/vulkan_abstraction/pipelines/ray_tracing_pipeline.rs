@@ -22,8 +22,9 @@ pub struct RaytracingPushConstant {
 /// Push-constant layout for the heap-mode (Slang) raytracing pipeline. Every
 /// `DescriptorHandle<T>` field in `shaders/rt_types.slang::RaytracingPC`
 /// lowers to a `uint2`, so each is mirrored here as `[u32; 2]` (low word =
-/// heap shader index, high word = 0). Total size: 144 bytes — well within
-/// the 256-byte minimum push-constant range required by Vulkan.
+/// heap shader index, high word = 0). Total size: 200 bytes (196 and the
+/// trailing alignment pad) — well within the 256-byte minimum push-constant
+/// range required by Vulkan.
 #[allow(dead_code)] // read by the gpu
 #[repr(C)]
 #[derive(Debug, Copy, Clone, Default)]
@@ -57,8 +58,30 @@ pub struct RaytracingHeapPushConstant {
     pub reservoirs_gi: [u64; 2],
     pub frame_count: u32,
     pub use_srgb: u32,
+    pub punctual_lights: [u32; 2],
+    /// Number of real entries in `emissive_indirection` / `punctual_lights`.
+    /// Both buffers are padded with a dummy element when empty, so the shader
+    /// can't use their size.
+    pub emissive_light_count: u32,
+    pub punctual_light_count: u32,
+    /// Buffer-device-address of the `EnvironmentInfo` parameters (see
+    /// `crate::environment`).
+    pub environment: u64,
+    /// Environment map sampling CDF (`float` structured buffer).
+    pub environment_cdf: [u32; 2],
+    /// Texture streaming feedback (`int` read-write structured buffer, one
+    /// entry per image slot; see [`vulkan_abstraction::TextureStreaming`]).
+    pub texture_feedback: [u32; 2],
+    /// The heap slot of each scene image (`uint` structured buffer), indexed
+    /// by the materials' `material_*_image` fields.
+    pub image_slots: [u32; 2],
+    /// Length of the ambient occlusion rays of `ray_gen_ao`; unused by the
+    /// path tracer.
+    pub ao_radius: f32,
 }
 
+const _: () = assert!(size_of::<RaytracingHeapPushConstant>() == 200);
+
 /// The four SPIR-V blobs a heap-mode ray-tracing pipeline links together — one
 /// per stage. The SBT/dispatch currently assumes exactly one raygen + one miss +
 /// one hit group (closest-hit + any-hit).
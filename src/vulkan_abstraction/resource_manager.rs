@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 use std::rc::Rc;
 
@@ -8,13 +8,69 @@ use crate::render_graph::graph::RenderGraph;
 use crate::render_graph::resource::Handle;
 use crate::vulkan_abstraction::image::sampler::SamplerDesc;
 use crate::vulkan_abstraction::{AccelerationStructure, ArenaBuffer, AsBuildJob, Buffer, EntityGpuData, Material};
-use crate::{error::SrResult, vulkan_abstraction};
+use crate::{
+    error::{SrError, SrResult},
+    vulkan_abstraction,
+};
 use vk_sync_fork as vk_sync;
 
 const ARENA_CAPACITY: vk::DeviceSize = 4096 * 16;
 
 //TODO handle growable
 
+/// The slots of a texture of a loaded scene: its sampler's heap slot, and the
+/// `image_slots` index of the image of each encoding its scene's materials
+/// sample it with, `NULL_TEXTURE_INDEX` for the other.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TextureSlots {
+    linear_image: u32,
+    srgb_image: u32,
+    sampler: u32,
+}
+
+impl TextureSlots {
+    /// `(image index, sampler heap slot)` for a slot reading texels
+    /// encoded as `encoding`. A texture edited into a slot of the other kind
+    /// falls back to the image it has.
+    fn resolve(&self, encoding: vulkan_abstraction::gltf::TexelEncoding) -> (u32, u32) {
+        let (preferred, other) = match encoding {
+            vulkan_abstraction::gltf::TexelEncoding::Srgb => (self.srgb_image, self.linear_image),
+            vulkan_abstraction::gltf::TexelEncoding::Linear => (self.linear_image, self.srgb_image),
+        };
+        let image = if preferred != Material::NULL_TEXTURE_INDEX {
+            preferred
+        } else {
+            other
+        };
+        (image, self.sampler)
+    }
+}
+
+/// The material of a registered BLAS as [`ResourceManager::set_material`]
+/// rewrites it.
+struct BlasMaterial {
+    /// The material as last set.
+    material: vulkan_abstraction::gltf::Material,
+    /// The slots of every texture of the scene the BLAS was loaded with, by
+    /// glTF texture index; empty for runtime meshes. Shared by the BLASes of
+    /// one scene.
+    texture_slots: Rc<[TextureSlots]>,
+    /// The BLAS's emissive triangles, whose emission is the material's.
+    emissive_triangles: Vec<vulkan_abstraction::gltf::EmissiveTriangle>,
+}
+
+impl BlasMaterial {
+    fn to_gpu(&self) -> Material {
+        let resolve = |texture_index: Option<usize>, encoding| {
+            texture_index
+                .and_then(|i| self.texture_slots.get(i))
+                .map(|slots| slots.resolve(encoding))
+                .unwrap_or((Material::NULL_TEXTURE_INDEX, Material::NULL_TEXTURE_INDEX))
+        };
+        Material::new(&self.material, &resolve)
+    }
+}
+
 /// Deferred work executed at the start of a specific absolute frame (see
 /// [`ResourceManager::start_of_frame`]).
 type FrameCallback<K> = Box<dyn FnOnce(&mut ResourceManager<K>) -> SrResult<()>>;
@@ -30,6 +86,9 @@ pub(crate) struct FrameInstanceData {
     pub transforms: Vec<vk::TransformMatrixKHR>,
     /// Dense `(emissive triangle slot, instance index)` table for NEE sampling.
     pub emissive_entries: Vec<vulkan_abstraction::gltf::EmissiveIndirectionEntry>,
+    /// Every registered punctual light, in registration order. The reservoirs
+    /// index lights by position, so the order must stay stable across frames.
+    pub punctual_lights: Vec<vulkan_abstraction::gltf::PunctualLight>,
 }
 
 //TODO there is structural decision to make on what to save and how cause raster needs a different way to store the geometry data probabibly with a features set constable?
@@ -47,11 +106,35 @@ pub(crate) struct ResourceManager<K: Hash + Eq + Copy> {
     meshes_info: vulkan_abstraction::ArenaGpuBuffer<EntityGpuData>,
     blas_emissive_triangles: vulkan_abstraction::ArenaGpuBuffer<vulkan_abstraction::gltf::EmissiveTriangle>,
     blases: HashMap<K, vulkan_abstraction::Blas>,
+    /// Key → skinning inputs of the skinned BLASes, whose vertex buffer the
+    /// skinning pass rewrites before they are refit.
+    skinned: HashMap<K, vulkan_abstraction::SkinnedGeometry>,
+    /// Key → morph target inputs of the morphed BLASes, blended into their
+    /// vertex buffer (or skinning rest pose) before they are refit.
+    morphed: HashMap<K, vulkan_abstraction::MorphedGeometry>,
     /// Key → slot in `meshes_info`.
     mesh_info_slots: HashMap<K, u32>,
     /// Key → slots of the BLAS's triangles in `blas_emissive_triangles`.
     emissive_triangle_slots: HashMap<K, Vec<u32>>,
-    images: HashMap<K, vulkan_abstraction::Image>,
+    /// Key → the BLAS's material, kept to be edited.
+    materials: HashMap<K, BlasMaterial>,
+    /// Keys of the BLASes with a double-sided material: their instances are
+    /// hit from both sides, the others' back faces are culled.
+    double_sided: HashSet<K>,
+    images: HashMap<K, vulkan_abstraction::StreamedTexture>,
+    /// Per scene image, the heap slot its resident levels are sampled through.
+    /// Materials refer to images by their index here, so streaming can move an
+    /// image to a fresh slot while frames in flight still sample the old one.
+    image_slots: vulkan_abstraction::ArenaGpuBuffer<u32>,
+    /// Key → index of the image in `image_slots`.
+    image_slot_indices: HashMap<K, u32>,
+    /// Uploads and evicts the mip levels of `images` on the hit shaders'
+    /// feedback (see [`Self::stream_textures`]).
+    texture_streaming: vulkan_abstraction::TextureStreaming,
+    /// World-space punctual lights. A `Vec` rather than a map: the per-frame
+    /// light list must come out in the same order every frame (see
+    /// [`FrameInstanceData::punctual_lights`]).
+    punctual_lights: Vec<(K, vulkan_abstraction::gltf::PunctualLight)>,
     /// Finite set of samplers, deduplicated by their description: glTF samplers
     /// don't need to be unique per texture. Never shrinks.
     samplers: HashMap<SamplerDesc, vulkan_abstraction::Sampler>,
@@ -62,6 +145,9 @@ pub(crate) struct ResourceManager<K: Hash + Eq + Copy> {
     /// transfer prologue in the render graph (`RenderGraph::add_prologue_buffer_copies`),
     /// so the copy rides the frame's submission ordered before the shader reads.
     buffer_copies_queued: Vec<(vk::Buffer, vk::Buffer, vk::BufferCopy)>,
+    /// Pending uploads of the images texture streaming swapped in, drained each
+    /// frame by [`Self::take_queued_texture_copies`] into the same prologue.
+    texture_copies_queued: Vec<vulkan_abstraction::TextureCopy>,
     /// Deferred work keyed by the absolute frame at whose start it must run
     /// (currently just deferred arena slot frees scheduled by `remove`). Drained
     /// by [`Self::start_of_frame`] — nothing runs unconditionally every frame.
@@ -79,6 +165,26 @@ pub(crate) struct ResourceManager<K: Hash + Eq + Copy> {
     core: Rc<vulkan_abstraction::Core>,
 }
 
+/// Facing flags of an instance. glTF front faces wind counter-clockwise,
+/// unless the instance's transform mirrors them (negative determinant), which
+/// makes them clockwise. Vulkan decides facing in object space and defaults to
+/// clockwise, so the flag has to follow the transform. Back faces are only
+/// culled for rays that ask for it, and never on double-sided geometry.
+fn instance_flags(double_sided: bool, transform: &vk::TransformMatrixKHR) -> vk::GeometryInstanceFlagsKHR {
+    let m = &transform.matrix;
+    let determinant =
+        m[0] * (m[5] * m[10] - m[6] * m[9]) - m[1] * (m[4] * m[10] - m[6] * m[8]) + m[2] * (m[4] * m[9] - m[5] * m[8]);
+
+    let mut flags = vk::GeometryInstanceFlagsKHR::empty();
+    if determinant >= 0.0 {
+        flags |= vk::GeometryInstanceFlagsKHR::TRIANGLE_FRONT_COUNTERCLOCKWISE;
+    }
+    if double_sided {
+        flags |= vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE;
+    }
+    flags
+}
+
 // `K: 'static` because deferred frame work is stored as boxed `FnOnce(&mut Self)`.
 impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
     pub fn new_empty(core: Rc<vulkan_abstraction::Core>) -> SrResult<Self> {
@@ -133,7 +239,21 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             vk::SamplerAddressMode::CLAMP_TO_EDGE,
             vk::SamplerAddressMode::CLAMP_TO_EDGE,
             vk::SamplerMipmapMode::LINEAR,
+            true,
+        )?;
+
+        let image_capacity = core
+            .descriptor_heap()
+            .resource_section_capacity(vulkan_abstraction::descriptor_heap::ResourceSection::Image);
+        let image_slots = vulkan_abstraction::ArenaGpuBuffer::new(
+            core.clone(),
+            image_capacity as vk::DeviceSize,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_SRC
+                | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            "image slots",
         )?;
+        let texture_streaming = vulkan_abstraction::TextureStreaming::new(Rc::clone(&core))?;
 
         Ok(Self {
             tlas,
@@ -141,13 +261,22 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             meshes_info,
             blas_emissive_triangles,
             blases: HashMap::new(),
+            skinned: HashMap::new(),
+            morphed: HashMap::new(),
             mesh_info_slots: HashMap::new(),
             emissive_triangle_slots: HashMap::new(),
+            double_sided: HashSet::new(),
+            materials: HashMap::new(),
             images: HashMap::new(),
+            image_slots,
+            image_slot_indices: HashMap::new(),
+            texture_streaming,
+            punctual_lights: Vec::new(),
             samplers: HashMap::new(),
             default_sampler,
 
             buffer_copies_queued: vec![],
+            texture_copies_queued: vec![],
             start_of_frame_callbacks: vec![],
             pending_blas_builds: vec![],
             core,
@@ -205,12 +334,46 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
         kept.into_iter().map(|i| copies[i]).collect()
     }
 
+    /// Queue the copies filling newly created geometry buffers and textures
+    /// (see [`vulkan_abstraction::GpuOnlyBuffer::new_with_upload`] and
+    /// [`vulkan_abstraction::Image::new_texture_with_upload`]) for the next
+    /// frame's prologue, keeping their staging buffers alive until that frame
+    /// is done.
+    pub fn queue_uploads(
+        &mut self,
+        buffer_uploads: Vec<vulkan_abstraction::BufferUpload>,
+        texture_uploads: Vec<vulkan_abstraction::TextureUpload>,
+    ) {
+        if buffer_uploads.is_empty() && texture_uploads.is_empty() {
+            return;
+        }
+        for upload in &buffer_uploads {
+            let (src, dst, region) = upload.copy;
+            self.queue_copy(src, dst, region);
+        }
+        self.texture_copies_queued
+            .extend(texture_uploads.iter().map(|upload| upload.copy.clone()));
+        self.schedule_at_frame(self.next_frame() + crate::MAX_FRAMES_IN_FLIGHT as u64, move |_| {
+            drop((buffer_uploads, texture_uploads));
+            Ok(())
+        });
+    }
+
+    /// Drain the queued texture uploads, for the renderer to record in the same
+    /// prologue as [`Self::take_queued_copies`] (see
+    /// [`RenderGraph::add_prologue_texture_copies`]). Their staging buffers are
+    /// kept alive here until the frame is done.
+    pub fn take_queued_texture_copies(&mut self) -> Vec<vulkan_abstraction::TextureCopy> {
+        std::mem::take(&mut self.texture_copies_queued)
+    }
+
     // ─── Per-frame data ──────────────────────────────────────────────────────
 
     /// Resolve the caller's per-frame `(key, transforms)` instance list into
     /// the raw arrays the frame needs: TLAS instances (custom index = the
     /// BLAS's stable mesh-info slot), the flat transform list (instance
-    /// order), and the emissive indirection entries. Pure resolution — the
+    /// order), the emissive indirection entries and the punctual lights. Pure
+    /// resolution — the
     /// renderer uploads the results into frame-local CpuToGpu buffers; nothing
     /// is stored here.
     pub fn frame_instance_data(&self, instances: &[(K, Vec<vk::TransformMatrixKHR>)]) -> SrResult<FrameInstanceData> {
@@ -239,14 +402,14 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
                 // Plain-data instance description; the BLAS device address is
                 // resolved late, inside `lower` (custom index = the BLAS's
                 // stable mesh-info slot; hit_group_offset = 0, same hit group
-                // for the whole scene; face culling disabled for simplicity).
+                // for the whole scene).
                 let desc = vulkan_abstraction::InstanceDesc {
                     blas: *key,
                     transform: *transform,
                     custom_index: mesh_info_slot,
                     mask: 0xFF,
                     sbt_offset: 0,
-                    flags: vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE,
+                    flags: instance_flags(self.double_sided.contains(key), transform),
                 };
                 as_instances.push(desc.lower(self));
 
@@ -263,6 +426,7 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             as_instances,
             transforms,
             emissive_entries,
+            punctual_lights: self.punctual_lights.iter().map(|(_, light)| *light).collect(),
         })
     }
 
@@ -285,12 +449,20 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
     /// Record every pending BLAS build (from `new_deferred` at load time) into
     /// `rg` as a build pass — importing each BLAS so the TLAS build orders itself
     /// after them — and re-evaluate the rebuild/update heuristic for any built
-    /// BLAS with no operation in flight. Returns, for each BLAS built this frame,
-    /// its `(key, imported handle)`: the handles feed [`Self::queue_tlas_build`] as
-    /// its dependencies, and the caller schedules each key's [`Self::mark_blas_built`]
-    /// for when this frame's GPU work completes (the graph records the build but
-    /// can't mutate this CPU-side heuristic state).
-    pub fn queue_blas_builds(&mut self, rg: &mut RenderGraph) -> SrResult<Vec<(K, Handle<AccelerationStructure>)>> {
+    /// BLAS with no operation in flight, deforming and refitting the deformed
+    /// ones that were given a new pose or new morph weights (`skinning_spirv`
+    /// and `morph_spirv` are the deformation passes' shaders). Returns, for
+    /// each BLAS built this frame, its `(key, imported handle)`: the handles
+    /// feed [`Self::queue_tlas_build`] as its dependencies, and the caller
+    /// schedules each key's [`Self::mark_blas_built`] for when this frame's GPU
+    /// work completes (the graph records the build but can't mutate this
+    /// CPU-side heuristic state).
+    pub fn queue_blas_builds(
+        &mut self,
+        rg: &mut RenderGraph,
+        skinning_spirv: &[u8],
+        morph_spirv: &[u8],
+    ) -> SrResult<Vec<(K, Handle<AccelerationStructure>)>> {
         let mut built = Vec::new();
 
         // 1. Pending initial builds. Their `op` was set by `new_deferred`; record
@@ -306,15 +478,56 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             built.push((key, handle));
         }
 
-        // 2. Steady-state heuristic for BLASes with nothing in flight. The renderer
-        //    doesn't track per-BLAS geometry mutation yet (its meshes are static
-        //    once loaded), so `inputs_changed = false` and `plan_op` yields `None`
-        //    — nothing is queued. This loop is the seam a future animated/skinned
-        //    mesh path drives an update/rebuild through.
-        for blas in self.blases.values_mut() {
-            if blas.op().is_none() {
-                let _ = blas.plan_op(false);
+        // 2. Steady-state heuristic for BLASes with nothing in flight. Only deformed
+        //    geometry changes once loaded: new morph weights are blended (into the
+        //    skinning rest pose, for a skinned mesh), a new pose is skinned into the
+        //    BLAS's vertex buffer, and the BLAS refit in place, so its address (baked
+        //    into this frame's instances already) stays valid. A deformed BLAS whose
+        //    last op is still in flight keeps its inputs pending for a later frame.
+        //    Idle frames record nothing, so they're folded in right away: that is
+        //    what advances a quiet deformed BLAS toward its settling rebuild.
+        let frame_slot = *self.core.absolute_frame_count.borrow() as usize % crate::MAX_FRAMES_IN_FLIGHT;
+        for (&key, blas) in self.blases.iter_mut() {
+            if blas.op().is_some() {
+                continue;
             }
+
+            let morph = match self.morphed.get_mut(&key) {
+                Some(morphed) => {
+                    let output = match self.skinned.get(&key) {
+                        Some(skinned) => skinned.rest_vertices_address(),
+                        None => blas.vertex_buffer().get_device_address(),
+                    };
+                    morphed.take_pending_weights(frame_slot, output)?
+                }
+                None => None,
+            };
+            let skinning = match self.skinned.get_mut(&key) {
+                Some(skinned) => {
+                    skinned.take_pending_pose(frame_slot, blas.vertex_buffer().get_device_address(), morph.is_some())?
+                }
+                None => None,
+            };
+
+            let Some(op) = blas.plan_op(morph.is_some() || skinning.is_some()) else {
+                blas.mark_built();
+                continue;
+            };
+
+            // The previous frame's trace may still read the BLAS (and its vertex
+            // buffer): import it in that state so the graph waits for it.
+            let handle = rg.import_with_usage(
+                blas.accel_arc(),
+                vk_sync::AccessType::RayTracingShaderReadAccelerationStructure,
+            );
+            if let Some(push_constant) = morph {
+                vulkan_abstraction::add_morph_pass(rg, morph_spirv, &handle, push_constant)?;
+            }
+            if let Some(push_constant) = skinning {
+                vulkan_abstraction::add_skinning_pass(rg, skinning_spirv, &handle, push_constant)?;
+            }
+            rg.add_as_refit_pass("blas_refit", &handle, blas.refit_job(op)?)?;
+            built.push((key, handle));
         }
 
         Ok(built)
@@ -366,18 +579,25 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
 
     /// Register every asset of a loaded scene, assigning each BLAS and image a
     /// fresh key from `make_key`. Materials get their texture references
-    /// resolved to descriptor-heap slots here (samplers are deduplicated into
-    /// the manager's finite sampler set). Returns the BLAS keys, parallel to
-    /// `blases`.
+    /// resolved to `image_slots` indices and sampler heap slots here (samplers are deduplicated into
+    /// the manager's finite sampler set), and the BLASes loaded with a deferred
+    /// build are queued for the next frame's graph. Returns the BLAS keys, parallel to
+    /// `blases`, and the deformed BLAS keys, parallel to `deformed_blases`.
     pub fn add_scene_assets(
         &mut self,
         blases: Vec<crate::LoadedBlas>,
+        deformed_blases: Vec<crate::LoadedDeformedBlas>,
         textures: Vec<vulkan_abstraction::gltf::Texture>,
         sampler_descs: Vec<SamplerDesc>,
-        images: Vec<vulkan_abstraction::Image>,
+        images: Vec<vulkan_abstraction::StreamedTexture>,
         make_key: &mut dyn FnMut() -> K,
-    ) -> SrResult<Vec<K>> {
-        let image_slots: Vec<u32> = images.iter().map(|image| image.sampled_slot()).collect();
+    ) -> SrResult<(Vec<K>, Vec<K>)> {
+        let mut image_indices = Vec::with_capacity(images.len());
+        for image in &images {
+            let (index, copy_region) = self.image_slots.allocate_and_update(&image.slot())?;
+            self.queue_copy(self.image_slots.inner_staging(), self.image_slots.inner(), copy_region);
+            image_indices.push(index as u32);
+        }
 
         let mut sampler_slots = Vec::with_capacity(sampler_descs.len());
         for desc in &sampler_descs {
@@ -385,53 +605,116 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
         }
         let default_sampler_slot = self.default_sampler.slot();
 
-        let resolve = |texture_index: Option<usize>| -> (u32, u32) {
-            match texture_index {
-                Some(i) => {
-                    let texture = &textures[i];
-                    let image_slot = image_slots[texture.source];
-                    let sampler_slot = texture.sampler.map(|s| sampler_slots[s]).unwrap_or(default_sampler_slot);
-                    (image_slot, sampler_slot)
-                }
-                None => (Material::NULL_TEXTURE_INDEX, Material::NULL_TEXTURE_INDEX),
-            }
-        };
+        let image_slot = |source: Option<usize>| source.map_or(Material::NULL_TEXTURE_INDEX, |i| image_indices[i]);
+        let texture_slots: Rc<[TextureSlots]> = textures
+            .iter()
+            .map(|texture| TextureSlots {
+                linear_image: image_slot(texture.source),
+                srgb_image: image_slot(texture.srgb_source),
+                sampler: texture.sampler.map(|s| sampler_slots[s]).unwrap_or(default_sampler_slot),
+            })
+            .collect();
 
         let mut keys = Vec::with_capacity(blases.len());
         for loaded in blases {
             let key = make_key();
-            let material = Material::new(&loaded.material, &resolve);
-            self.add_blas(key, loaded.blas, material, &loaded.emissive_triangles)?;
+            self.add_blas(
+                key,
+                loaded.blas,
+                loaded.material,
+                Rc::clone(&texture_slots),
+                loaded.emissive_triangles,
+            )?;
+            if let Some(build_job) = loaded.build_job {
+                self.queue_blas_build_job(key, build_job);
+            }
             keys.push(key);
         }
 
-        for image in images {
-            self.images.insert(make_key(), image);
+        let mut deformed_keys = Vec::with_capacity(deformed_blases.len());
+        for deformed in deformed_blases {
+            let key = make_key();
+            self.add_blas(
+                key,
+                deformed.loaded.blas,
+                deformed.loaded.material,
+                Rc::clone(&texture_slots),
+                deformed.loaded.emissive_triangles,
+            )?;
+            if let Some(build_job) = deformed.loaded.build_job {
+                self.queue_blas_build_job(key, build_job);
+            }
+            if let Some(skinning) = deformed.skinning {
+                self.skinned.insert(key, skinning);
+            }
+            if let Some(morphing) = deformed.morphing {
+                self.morphed.insert(key, morphing);
+            }
+            deformed_keys.push(key);
         }
 
-        Ok(keys)
+        for (image, index) in images.into_iter().zip(image_indices) {
+            let key = make_key();
+            self.images.insert(key, image);
+            self.image_slot_indices.insert(key, index);
+        }
+
+        Ok((keys, deformed_keys))
+    }
+
+    /// Pose the skinned BLAS registered under `key` for the next frame (see
+    /// [`vulkan_abstraction::SkinnedGeometry::set_joint_matrices`]).
+    pub fn set_joint_matrices(&mut self, key: &K, joint_matrices: &[nalgebra::Matrix4<f32>]) -> SrResult<()> {
+        match self.skinned.get_mut(key) {
+            Some(skinned) => skinned.set_joint_matrices(joint_matrices),
+            None => Err(SrError::new_custom(
+                "set_joint_matrices: no skinned mesh is registered under this key".to_string(),
+            )),
+        }
+    }
+
+    /// Set the morph target weights of the morphed BLAS registered under `key`
+    /// for the next frame (see [`vulkan_abstraction::MorphedGeometry::set_weights`]).
+    pub fn set_morph_weights(&mut self, key: &K, weights: &[f32]) -> SrResult<()> {
+        match self.morphed.get_mut(key) {
+            Some(morphed) => morphed.set_weights(weights),
+            None => Err(SrError::new_custom(
+                "set_morph_weights: no mesh with morph targets is registered under this key".to_string(),
+            )),
+        }
     }
 
     /// Register a BLAS under `key`: uploads its mesh info (slot becomes the
     /// instance custom index) and its local-space emissive triangles.
+    /// `texture_slots` resolves the material's texture indices to heap slots
+    /// (see [`BlasMaterial::texture_slots`]).
     pub fn add_blas(
         &mut self,
         key: K,
         blas: vulkan_abstraction::Blas,
-        material: Material,
-        emissive_triangles: &[vulkan_abstraction::gltf::EmissiveTriangle],
+        material: vulkan_abstraction::gltf::Material,
+        texture_slots: Rc<[TextureSlots]>,
+        emissive_triangles: Vec<vulkan_abstraction::gltf::EmissiveTriangle>,
     ) -> SrResult<()> {
+        let blas_material = BlasMaterial {
+            material,
+            texture_slots,
+            emissive_triangles,
+        };
         let gpu_data = EntityGpuData {
             vertex_buffer: blas.vertex_buffer().get_device_address(),
             index_buffer: blas.index_buffer().get_device_address(),
-            material,
+            material: blas_material.to_gpu(),
         };
         let (slot, copy_region) = self.meshes_info.allocate_and_update(&gpu_data)?;
         self.queue_copy(self.meshes_info.inner_staging(), self.meshes_info.inner(), copy_region);
         self.mesh_info_slots.insert(key, slot as u32);
+        if blas_material.material.double_sided {
+            self.double_sided.insert(key);
+        }
 
-        let mut tri_slots = Vec::with_capacity(emissive_triangles.len());
-        for tri in emissive_triangles {
+        let mut tri_slots = Vec::with_capacity(blas_material.emissive_triangles.len());
+        for tri in &blas_material.emissive_triangles {
             let (tri_slot, tri_copy) = self.blas_emissive_triangles.allocate_and_update(tri)?;
             self.queue_copy(
                 self.blas_emissive_triangles.inner_staging(),
@@ -442,16 +725,106 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
         }
         self.emissive_triangle_slots.insert(key, tri_slots);
 
+        self.materials.insert(key, blas_material);
         self.blases.insert(key, blas);
         Ok(())
     }
 
-    /// Whether `key` currently has any asset (BLAS or image) registered.
+    /// The material of the BLAS registered under `key`, as last set.
+    pub fn material(&self, key: &K) -> Option<&vulkan_abstraction::gltf::Material> {
+        self.materials.get(key).map(|material| &material.material)
+    }
+
+    /// Replace the material of the BLAS registered under `key`, from the next
+    /// frame on: its mesh info slot and the emission of its emissive triangles
+    /// are rewritten through the staging-copy queue, like at load time.
+    /// Texture indices refer to the textures of the scene the BLAS was loaded
+    /// with.
+    ///
+    /// Two things are fixed when the BLAS is built and can't change here: a
+    /// BLAS built opaque never runs the any-hit shader, so it can't become
+    /// masked or blended, and only meshes emissive when loaded are sampled as
+    /// lights.
+    pub fn set_material(&mut self, key: &K, material: &vulkan_abstraction::gltf::Material) -> SrResult<()> {
+        let (Some(blas), Some(&slot), Some(current)) = (
+            self.blases.get(key),
+            self.mesh_info_slots.get(key),
+            self.materials.get_mut(key),
+        ) else {
+            return Err(SrError::new_custom(
+                "set_material: no mesh is registered under this key".to_string(),
+            ));
+        };
+
+        if blas.is_opaque() && !material.is_opaque() {
+            return Err(SrError::new_custom(format!(
+                "set_material: the mesh was loaded opaque and can't switch to alpha mode {:?}",
+                material.alpha_mode
+            )));
+        }
+        if let Some(index) = material
+            .texture_indices()
+            .into_iter()
+            .filter_map(|(index, _)| index)
+            .find(|&index| index >= current.texture_slots.len())
+        {
+            return Err(SrError::new_custom(format!(
+                "set_material: the mesh's scene has no texture {index}"
+            )));
+        }
+
+        current.material = material.clone();
+        let emission = [
+            material.emissive_factor[0] * material.emissive_strength,
+            material.emissive_factor[1] * material.emissive_strength,
+            material.emissive_factor[2] * material.emissive_strength,
+            0.0,
+        ];
+        for tri in &mut current.emissive_triangles {
+            tri.emission = emission;
+        }
+
+        let gpu_data = EntityGpuData {
+            vertex_buffer: blas.vertex_buffer().get_device_address(),
+            index_buffer: blas.index_buffer().get_device_address(),
+            material: current.to_gpu(),
+        };
+        let copy_region = self.meshes_info.update(slot as usize, &gpu_data)?;
+        self.queue_copy(self.meshes_info.inner_staging(), self.meshes_info.inner(), copy_region);
+
+        // copied out: `queue_copy` borrows the whole manager
+        let tri_slots = self.emissive_triangle_slots.get(key).cloned().unwrap_or_default();
+        for (i, tri_slot) in tri_slots.into_iter().enumerate() {
+            let tri_copy = self
+                .blas_emissive_triangles
+                .update(tri_slot as usize, &self.materials[key].emissive_triangles[i])?;
+            self.queue_copy(
+                self.blas_emissive_triangles.inner_staging(),
+                self.blas_emissive_triangles.inner(),
+                tri_copy,
+            );
+        }
+
+        if material.double_sided {
+            self.double_sided.insert(*key);
+        } else {
+            self.double_sided.remove(key);
+        }
+        Ok(())
+    }
+
+    /// Register a world-space punctual light under `key`. Lights live in CPU
+    /// memory only: the renderer uploads the whole list every frame.
+    pub fn add_light(&mut self, key: K, light: vulkan_abstraction::gltf::PunctualLight) {
+        self.punctual_lights.push((key, light));
+    }
+
+    /// Whether `key` currently has any asset (BLAS, image or light) registered.
     pub fn contains(&self, key: &K) -> bool {
-        self.blases.contains_key(key) || self.images.contains_key(key)
+        self.blases.contains_key(key) || self.images.contains_key(key) || self.punctual_lights.iter().any(|(k, _)| k == key)
     }
 
-    /// Remove whatever asset `key` refers to (BLAS and/or image). Arena slots
+    /// Remove whatever asset `key` refers to (BLAS, image and/or light). Arena slots
     /// are deferred-freed (reclaimed by a start-of-frame callback scheduled for
     /// the frame at which no in-flight frame can still read them); the BLAS /
     /// image objects are dropped immediately, so the caller must guarantee the
@@ -469,7 +842,16 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             }
         }
         self.blases.remove(key);
+        self.double_sided.remove(key);
+        self.materials.remove(key);
+        self.skinned.remove(key);
+        self.morphed.remove(key);
         self.images.remove(key);
+        if let Some(index) = self.image_slot_indices.remove(key) {
+            self.image_slots.free_index(index as usize);
+            any_freed = true;
+        }
+        self.punctual_lights.retain(|(k, _)| k != key);
 
         if any_freed {
             // The arenas tag each free with the frame it happened on and only
@@ -481,11 +863,64 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
             self.schedule_at_frame(due, |rm| {
                 rm.meshes_info.process_pending_frees();
                 rm.blas_emissive_triangles.process_pending_frees();
+                rm.image_slots.process_pending_frees();
                 Ok(())
             });
         }
     }
 
+    // ─── Texture streaming ───────────────────────────────────────────────────
+
+    /// Stream the mip levels of the scene images for `upcoming_frame` (see
+    /// [`vulkan_abstraction::TextureStreaming::update`]), once the frame that
+    /// last used its slot is done. The images swapped in are uploaded and
+    /// published in `image_slots` by the frame's prologue; the ones swapped
+    /// out, their slots and the staging buffers are dropped at the frame no
+    /// in-flight frame can still use them. Returns the heap slot of the
+    /// feedback buffer `upcoming_frame`'s hit shaders write to.
+    pub fn stream_textures(&mut self, upcoming_frame: u64) -> SrResult<u32> {
+        let (keys, mut textures): (Vec<K>, Vec<&mut vulkan_abstraction::StreamedTexture>) =
+            self.images.iter_mut().map(|(key, image)| (*key, image)).unzip();
+        let slots_before: Vec<u32> = textures.iter().map(|texture| texture.slot()).collect();
+        let changes = self.texture_streaming.update(&mut textures, upcoming_frame)?;
+        let moved: Vec<(K, u32)> = keys
+            .into_iter()
+            .zip(textures)
+            .zip(slots_before)
+            .filter(|((_, texture), before)| texture.slot() != *before)
+            .map(|((key, texture), _)| (key, texture.slot()))
+            .collect();
+        if moved.is_empty() {
+            return Ok(self.texture_streaming.feedback_slot(upcoming_frame));
+        }
+
+        for (key, slot) in moved {
+            let index = self.image_slot_indices[&key] as usize;
+            let copy_region = self.image_slots.update(index, &slot)?;
+            self.queue_copy(self.image_slots.inner_staging(), self.image_slots.inner(), copy_region);
+        }
+        let vulkan_abstraction::StreamingChanges { retired, uploads } = changes;
+        self.texture_copies_queued
+            .extend(uploads.iter().map(|upload| upload.copy.clone()));
+        self.schedule_at_frame(upcoming_frame + crate::MAX_FRAMES_IN_FLIGHT as u64, move |_| {
+            drop((retired, uploads));
+            Ok(())
+        });
+
+        Ok(self.texture_streaming.feedback_slot(upcoming_frame))
+    }
+
+    /// Cap the GPU memory the mip levels of the scene images take, evicting
+    /// from the next frame on whatever doesn't fit.
+    pub fn set_texture_budget(&mut self, bytes: u64) {
+        self.texture_streaming.set_budget(bytes);
+    }
+
+    /// The bytes of the mip levels of the scene images on the GPU.
+    pub fn resident_texture_bytes(&self) -> u64 {
+        self.images.values().map(|image| image.resident_bytes()).sum()
+    }
+
     /// Slot of the (deduplicated) sampler matching `desc`, creating it on first
     /// use. The sampler set only grows — samplers are never removed.
     fn sampler_slot(&mut self, desc: &SamplerDesc) -> SrResult<u32> {
@@ -514,6 +949,10 @@ impl<K: Hash + Eq + Copy + 'static> ResourceManager<K> {
         self.blas_emissive_triangles.raw().storage_slot()
     }
 
+    pub fn image_slots_storage_slot(&self) -> u32 {
+        self.image_slots.raw().storage_slot()
+    }
+
     // ─── Internal helpers ────────────────────────────────────────────────────
 
     fn queue_copy(&mut self, src: vk::Buffer, dst: vk::Buffer, region: vk::BufferCopy) {
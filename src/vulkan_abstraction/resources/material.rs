@@ -1,13 +1,16 @@
 use crate::vulkan_abstraction;
+use crate::vulkan_abstraction::gltf::TexelEncoding;
 
-/// GPU-ready material. Texture references are stored as *resolved descriptor
-/// heap slots* (`(image sampled slot, sampler slot)` pairs), filled in at
-/// scene-load time — the shaders dereference the heap directly, there is no
-/// texture indirection buffer. A missing texture is `NULL_TEXTURE_INDEX` in
-/// the image slot (the sampler slot is then ignored by the shader).
+/// GPU-ready material. Texture references are stored as `(image index,
+/// sampler slot)` pairs, filled in at scene-load time: the sampler is a
+/// resolved descriptor heap slot, the image an index into the image slot
+/// indirection buffer, which follows the image as texture streaming moves it
+/// between heap slots. A missing texture is `NULL_TEXTURE_INDEX` in the image
+/// index (the sampler slot is then ignored by the shader). Each slot
+/// also carries its `KHR_texture_transform` as the two rows of a 2x3 matrix.
 ///
 /// Layout mirrors the inlined `material_*` fields of
-/// `shaders/rt_types.slang::MeshInfo` exactly (112 bytes). `MeshInfo` is read
+/// `shaders/rt_types.slang::MeshInfo` exactly (384 bytes). `MeshInfo` is read
 /// through a `StructuredBuffer` (std430), so the explicit pads keep every
 /// `float4` at a 16-aligned offset *within MeshInfo* (this struct starts at
 /// offset 16 after the two buffer pointers) and pad the total `MeshInfo` out
@@ -41,22 +44,60 @@ pub struct Material {
     emissive_image: u32,
     emissive_sampler: u32,
 
-    _pad_end: [u32; 2],
+    occlusion_strength: f32,
+    _pad_end: u32,
+
+    base_color_uv_transform: [[f32; 4]; 2],
+    metallic_roughness_uv_transform: [[f32; 4]; 2],
+    normal_uv_transform: [[f32; 4]; 2],
+    occlusion_uv_transform: [[f32; 4]; 2],
+    emissive_uv_transform: [[f32; 4]; 2],
+
+    //rgb + roughness
+    sheen: [f32; 4],
+    //rgb color + factor
+    specular: [f32; 4],
+
+    clearcoat_factor: f32,
+    clearcoat_roughness_factor: f32,
+    clearcoat_normal_image: u32,
+    clearcoat_normal_sampler: u32,
+    clearcoat_normal_uv_transform: [[f32; 4]; 2],
+
+    //rgb + distance, 0 for a medium that absorbs nothing
+    attenuation: [f32; 4],
+    thickness_factor: f32,
+    pub double_sided: u32,
+    _pad_volume: [f32; 2],
 }
 
 impl Material {
     pub(crate) const NULL_TEXTURE_INDEX: u32 = u32::MAX;
 
+    /// `alpha_mode` values, as `shaders/any_hit.slang` reads them.
+    pub(crate) const ALPHA_MODE_OPAQUE: u32 = 0;
+    pub(crate) const ALPHA_MODE_MASK: u32 = 1;
+    pub(crate) const ALPHA_MODE_BLEND: u32 = 2;
+
     /// Build the GPU material from the glTF one. `resolve` maps a glTF texture
-    /// index (`Option<usize>`) to its `(image heap slot, sampler heap slot)`
-    /// pair, returning `NULL_TEXTURE_INDEX` slots for `None`.
-    pub(crate) fn new(material: &vulkan_abstraction::gltf::Material, resolve: &impl Fn(Option<usize>) -> (u32, u32)) -> Self {
+    /// index (`Option<usize>`) to the `(image index, sampler heap slot)`
+    /// pair of its image in the slot's encoding, returning
+    /// `NULL_TEXTURE_INDEX` slots for `None`.
+    pub(crate) fn new(
+        material: &vulkan_abstraction::gltf::Material,
+        resolve: &impl Fn(Option<usize>, TexelEncoding) -> (u32, u32),
+    ) -> Self {
+        let [
+            (base_color_image, base_color_sampler),
+            (metallic_roughness_image, metallic_roughness_sampler),
+            (normal_image, normal_sampler),
+            (occlusion_image, occlusion_sampler),
+            (emissive_image, emissive_sampler),
+            (clearcoat_normal_image, clearcoat_normal_sampler),
+        ] = material
+            .texture_indices()
+            .map(|(texture_index, encoding)| resolve(texture_index, encoding));
         let pbr = &material.pbr_metallic_roughness_properties;
-        let (base_color_image, base_color_sampler) = resolve(pbr.base_color_texture_index);
-        let (metallic_roughness_image, metallic_roughness_sampler) = resolve(pbr.metallic_roughness_texture_index);
-        let (normal_image, normal_sampler) = resolve(material.normal_texture_index);
-        let (occlusion_image, occlusion_sampler) = resolve(material.occlusion_texture_index);
-        let (emissive_image, emissive_sampler) = resolve(material.emissive_texture_index);
 
         Self {
             base_color_value: pbr.base_color_factor,
@@ -71,12 +112,16 @@ impl Material {
                 material.emissive_strength,
             ],
 
-            alpha_mode: 0,
-            alpha_cutoff: 0.0,
+            alpha_mode: match material.alpha_mode {
+                gltf::material::AlphaMode::Opaque => Self::ALPHA_MODE_OPAQUE,
+                gltf::material::AlphaMode::Mask => Self::ALPHA_MODE_MASK,
+                gltf::material::AlphaMode::Blend => Self::ALPHA_MODE_BLEND,
+            },
+            alpha_cutoff: material.alpha_cutoff,
             transmission_factor: material.transmission_factor,
             ior: material.ior,
             _pad_mid: [0.0; 2],
-            _pad_end: [0; 2],
+            _pad_end: 0,
 
             base_color_image,
             base_color_sampler,
@@ -88,6 +133,47 @@ impl Material {
             occlusion_sampler,
             emissive_image,
             emissive_sampler,
+            occlusion_strength: material.occlusion_strength,
+
+            base_color_uv_transform: pbr.base_color_texture_transform.rows(),
+            metallic_roughness_uv_transform: pbr.metallic_roughness_texture_transform.rows(),
+            normal_uv_transform: material.normal_texture_transform.rows(),
+            occlusion_uv_transform: material.occlusion_texture_transform.rows(),
+            emissive_uv_transform: material.emissive_texture_transform.rows(),
+
+            sheen: [
+                material.sheen_color_factor[0],
+                material.sheen_color_factor[1],
+                material.sheen_color_factor[2],
+                material.sheen_roughness_factor,
+            ],
+            specular: [
+                material.specular_color_factor[0],
+                material.specular_color_factor[1],
+                material.specular_color_factor[2],
+                material.specular_factor,
+            ],
+
+            clearcoat_factor: material.clearcoat_factor,
+            clearcoat_roughness_factor: material.clearcoat_roughness_factor,
+            clearcoat_normal_image,
+            clearcoat_normal_sampler,
+            clearcoat_normal_uv_transform: material.clearcoat_normal_texture_transform.rows(),
+
+            attenuation: [
+                material.attenuation_color[0],
+                material.attenuation_color[1],
+                material.attenuation_color[2],
+                // thin walls enclose no medium
+                if material.thickness_factor > 0.0 && material.attenuation_distance.is_finite() {
+                    material.attenuation_distance
+                } else {
+                    0.0
+                },
+            ],
+            thickness_factor: material.thickness_factor,
+            double_sided: material.double_sided as u32,
+            _pad_volume: [0.0; 2],
         }
     }
 }
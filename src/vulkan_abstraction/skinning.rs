@@ -0,0 +1,174 @@
+use std::rc::Rc;
+
+use ash::vk;
+use nalgebra as na;
+
+use crate::error::{SrError, SrResult};
+use crate::render_graph::graph::RenderGraph;
+use crate::render_graph::pass_builder::{ComputeRenderPassBuilder, ComputeShaders, PassCommonDataBuilder, ShaderSource};
+use crate::render_graph::resource::Handle;
+use crate::utils::na_mat4_to_vk_transform;
+use crate::vulkan_abstraction::{
+    self, AccelerationStructure, Buffer, GpuOnlyBuffer, HostAccessibleBuffer, SkinningPushConstant, StagingBuffer,
+};
+use vk_sync_fork as vk_sync;
+
+/// Threads per workgroup of `shaders/skinning.slang`.
+const SKINNING_WORKGROUP_SIZE: u32 = 64;
+
+/// What the skinning pass needs to deform one skinned BLAS: its rest-pose
+/// vertices, their joints and weights, and the joint transforms of the pose
+/// to skin into. The deformed vertices are written into the BLAS's own vertex
+/// buffer, which is then refit.
+///
+/// The joint transforms live in one host-visible buffer per frame slot (like
+/// the renderer's camera matrices), so writing this frame's pose can't race a
+/// frame still in flight.
+///
+/// A mesh with morph targets too is blended into `rest_vertices` first: the
+/// current pose is kept to skin the new rest pose with.
+pub struct SkinnedGeometry {
+    rest_vertices: GpuOnlyBuffer,
+    skin_vertices: GpuOnlyBuffer,
+    vertex_count: u32,
+    joint_count: usize,
+    joint_transforms_pool: Vec<StagingBuffer<vk::TransformMatrixKHR>>,
+    joint_transforms: Vec<vk::TransformMatrixKHR>,
+    /// Set by [`Self::set_joint_matrices`], cleared by the next frame that
+    /// skins this geometry.
+    pose_pending: bool,
+}
+
+impl SkinnedGeometry {
+    /// Create the buffers of a skinned mesh's rest pose, with its first pose
+    /// pending: the first frame it's rendered in skins it. Returns them with
+    /// the uploads filling them, to record before that frame's passes (see
+    /// [`GpuOnlyBuffer::new_with_upload`]).
+    pub fn new(
+        core: Rc<vulkan_abstraction::Core>,
+        rest_vertices: &[vulkan_abstraction::gltf::Vertex],
+        skin_vertices: &[vulkan_abstraction::gltf::SkinVertex],
+        joint_matrices: &[na::Matrix4<f32>],
+    ) -> SrResult<(Self, Vec<vulkan_abstraction::BufferUpload>)> {
+        if rest_vertices.len() != skin_vertices.len() {
+            return Err(SrError::new_custom(format!(
+                "skinning: {} vertices but {} joints/weights",
+                rest_vertices.len(),
+                skin_vertices.len()
+            )));
+        }
+        if joint_matrices.is_empty() {
+            return Err(SrError::new_custom("skinning: a skin needs at least one joint".to_string()));
+        }
+
+        let usage = vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS;
+        let (rest_vertices_buffer, rest_vertices_upload) =
+            GpuOnlyBuffer::new_with_upload(Rc::clone(&core), rest_vertices, usage, "skinning rest pose vertices")?;
+        let (skin_vertices_buffer, skin_vertices_upload) =
+            GpuOnlyBuffer::new_with_upload(Rc::clone(&core), skin_vertices, usage, "skinning joints and weights")?;
+        let joint_transforms_pool = (0..crate::MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                StagingBuffer::new(
+                    Rc::clone(&core),
+                    joint_matrices.len() as u64,
+                    usage,
+                    "skinning joint transforms",
+                )
+            })
+            .collect::<SrResult<Vec<_>>>()?;
+
+        let mut skinned = Self {
+            rest_vertices: rest_vertices_buffer,
+            skin_vertices: skin_vertices_buffer,
+            vertex_count: rest_vertices.len() as u32,
+            joint_count: joint_matrices.len(),
+            joint_transforms_pool,
+            joint_transforms: vec![],
+            pose_pending: false,
+        };
+        skinned.set_joint_matrices(joint_matrices)?;
+        Ok((
+            skinned,
+            rest_vertices_upload.into_iter().chain(skin_vertices_upload).collect(),
+        ))
+    }
+
+    pub fn joint_count(&self) -> usize {
+        self.joint_count
+    }
+
+    /// Pose the mesh for the next frame: one skinning matrix per joint of the
+    /// skin (see `gltf::Skin::joint_matrices`). Setting several poses before a
+    /// frame picks them up keeps the last one.
+    pub fn set_joint_matrices(&mut self, joint_matrices: &[na::Matrix4<f32>]) -> SrResult<()> {
+        if joint_matrices.len() != self.joint_count {
+            return Err(SrError::new_custom(format!(
+                "skinning: got {} joint matrices for a skin with {} joints",
+                joint_matrices.len(),
+                self.joint_count
+            )));
+        }
+
+        self.joint_transforms = joint_matrices.iter().map(|m| na_mat4_to_vk_transform(*m)).collect();
+        self.pose_pending = true;
+        Ok(())
+    }
+
+    /// Device address of the rest pose the skinning pass reads, which the
+    /// morph target pass blends into.
+    pub(crate) fn rest_vertices_address(&self) -> vk::DeviceAddress {
+        self.rest_vertices.get_device_address()
+    }
+
+    /// If a pose is pending, or the rest pose was just rewritten, write the
+    /// pose into `frame_slot`'s joint buffer and return the push constant of
+    /// the pass skinning it into `output_vertices`.
+    pub(crate) fn take_pending_pose(
+        &mut self,
+        frame_slot: usize,
+        output_vertices: vk::DeviceAddress,
+        rest_pose_changed: bool,
+    ) -> SrResult<Option<SkinningPushConstant>> {
+        if !self.pose_pending && !rest_pose_changed {
+            return Ok(None);
+        }
+        self.pose_pending = false;
+
+        let joint_transforms_buffer = &mut self.joint_transforms_pool[frame_slot];
+        joint_transforms_buffer.map_mut()?.copy_from_slice(&self.joint_transforms);
+
+        Ok(Some(SkinningPushConstant {
+            rest_vertices: self.rest_vertices.get_device_address(),
+            skin_vertices: self.skin_vertices.get_device_address(),
+            joint_transforms: joint_transforms_buffer.get_device_address(),
+            output_vertices,
+            vertex_count: self.vertex_count,
+            _pad: 0,
+        }))
+    }
+}
+
+/// Skinning graph node (heap + Slang): deforms the rest pose of a skinned
+/// BLAS into its vertex buffer, ahead of the BLAS's refit.
+///
+/// The vertex buffer isn't a graph resource, so the pass declares its write on
+/// the BLAS it feeds: the refit build pass writes the same handle, and the
+/// graph orders the two with a (global) barrier.
+pub(crate) fn add_skinning_pass(
+    rg: &mut RenderGraph,
+    spirv: &[u8],
+    blas_h: &Handle<AccelerationStructure>,
+    push_constant: SkinningPushConstant,
+) -> SrResult<()> {
+    let mut common = PassCommonDataBuilder::new(rg, "skinning");
+    common.write(blas_h, vk_sync::AccessType::ComputeShaderWrite)?;
+
+    let group_count = push_constant.vertex_count.div_ceil(SKINNING_WORKGROUP_SIZE);
+    let pass = ComputeRenderPassBuilder::default()
+        .common(common.build())
+        .shaders(ComputeShaders::new(vec![ShaderSource::Spirv(spirv.to_vec())], 0, "main"))
+        .generate_render(rg, [group_count, 1, 1], move |_| Ok(push_constant))
+        .map_err(|e| SrError::new_custom(format!("skinning pass builder failed: {e}")))?;
+    rg.add_render_pass(pass);
+    Ok(())
+}